@@ -0,0 +1,17 @@
+fn main() {
+    println!("cargo:rerun-if-changed=Cargo.lock");
+    let version = std::fs::read_to_string("Cargo.lock")
+        .ok()
+        .and_then(|lock| locked_version(&lock, "skia-safe"))
+        .unwrap_or_else(|| "unknown".to_string());
+    println!("cargo:rustc-env=SKIA_SAFE_VERSION={}", version);
+}
+
+/// Finds the resolved version of `package` in the lock file.
+fn locked_version(lock: &str, package: &str) -> Option<String> {
+    let name_line = format!("name = \"{}\"", package);
+    let mut lines = lock.lines().skip_while(|line| *line != name_line);
+    lines.next()?;
+    let version = lines.next()?.strip_prefix("version = \"")?;
+    Some(version.trim_end_matches('"').to_string())
+}
@@ -0,0 +1,190 @@
+use crate::config::BenchmarkConfig;
+use crate::error::BenchError;
+use crate::render::{
+    bytes_from_file_path, check_file_exists, data_from_file_path, find_svgs, load_font_collection,
+    load_typeface,
+};
+use log::debug;
+use skia_safe::image::CachingHint;
+use skia_safe::svg::Dom;
+use skia_safe::textlayout::FontCollection;
+use skia_safe::{Image, Typeface};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+static STREAMED_SVG: OnceLock<Vec<u8>> = OnceLock::new();
+
+/// Inputs decoded once before the loop with `--cache-assets`.
+///
+/// Anything not cached is loaded from disk on each use, which is the default.
+#[derive(Default)]
+pub(crate) struct Assets {
+    image: Option<Image>,
+    font_collection: Option<FontCollection>,
+    /// The font collection with `--fallback-font` registered as the `Fallback` family.
+    fallback_collection: Option<FontCollection>,
+    typeface: Option<Typeface>,
+    svg: Option<Dom>,
+    /// The SVGs matched by `--svg-glob`, in match order.
+    svg_grid: Vec<Dom>,
+}
+
+impl Assets {
+    /// Loads the inputs of the enabled stages if `--cache-assets` is set, and nothing otherwise.
+    pub(crate) fn new(config: &BenchmarkConfig) -> Result<Self, BenchError> {
+        let working_path = &config.dir_path;
+        let mut assets = Assets::default();
+        if config.draw_svg && config.svg_glob.is_none() && config.svg_from_stream() {
+            let svg_data = streamed_svg_bytes(config)?;
+            let svg = Dom::from_bytes(svg_data)
+                .map_err(|_| BenchError::DecodeFailed("the streamed SVG".to_string()))?;
+            assets.svg = Some(svg);
+        }
+        if !config.cache_assets {
+            return Ok(assets);
+        }
+        if config.draw_raster
+            || config.blur_sigma.is_some()
+            || !config.filter_chain.is_empty()
+            || config.preset.is_some()
+        {
+            let raster_path = check_file_exists(working_path.join(&config.raster_file))?;
+            assets.image = Some(load_image(&raster_path)?);
+        }
+        if config.draw_text {
+            let font_path = check_file_exists(working_path.join(&config.font_file))?;
+            assets.font_collection = Some(load_font_collection(&font_path, None)?);
+            if let Some(fallback_font) = &config.fallback_font {
+                let fallback_path = check_file_exists(working_path.join(fallback_font))?;
+                assets.fallback_collection =
+                    Some(load_font_collection(&font_path, Some(&fallback_path))?);
+            }
+        }
+        if config.preset.is_some() || config.simple_text || config.text_on_path {
+            let font_path = check_file_exists(working_path.join(&config.font_file))?;
+            assets.typeface = Some(load_typeface(&font_path)?);
+        }
+        if config.draw_svg && config.svg_glob.is_none() && assets.svg.is_none() {
+            let svg_path = check_file_exists(working_path.join(&config.svg_file))?;
+            assets.svg = Some(load_svg(&svg_path)?);
+        }
+        if let (true, Some(pattern)) = (config.draw_svg, &config.svg_glob) {
+            let svg_paths = find_svgs(working_path, pattern)?;
+            assets.svg_grid = svg_paths
+                .iter()
+                .map(|svg_path| load_svg(svg_path))
+                .collect::<Result<_, _>>()?;
+        }
+        Ok(assets)
+    }
+
+    pub(crate) fn image(&self, raster_path: &Path) -> Result<Image, BenchError> {
+        match &self.image {
+            Some(image) => Ok(image.clone()),
+            None => load_image(raster_path),
+        }
+    }
+
+    /// The raster image decoded into memory, so that drawing or uploading it does not decode it.
+    pub(crate) fn decoded_image(&self, raster_path: &Path) -> Result<Image, BenchError> {
+        self.image(raster_path)?
+            .make_raster_image(CachingHint::Allow)
+            .ok_or_else(|| BenchError::DecodeFailed(raster_path.display().to_string()))
+    }
+
+    pub(crate) fn font_collection(&self, font_path: &Path) -> Result<FontCollection, BenchError> {
+        match &self.font_collection {
+            Some(font_collection) => Ok(font_collection.clone()),
+            None => load_font_collection(font_path, None),
+        }
+    }
+
+    pub(crate) fn fallback_collection(
+        &self,
+        font_path: &Path,
+        fallback_path: &PathBuf,
+    ) -> Result<FontCollection, BenchError> {
+        match &self.fallback_collection {
+            Some(font_collection) => Ok(font_collection.clone()),
+            None => load_font_collection(font_path, Some(fallback_path)),
+        }
+    }
+
+    pub(crate) fn typeface(&self, font_path: &Path) -> Result<Typeface, BenchError> {
+        match &self.typeface {
+            Some(typeface) => Ok(typeface.clone()),
+            None => load_typeface(font_path),
+        }
+    }
+
+    /// The cached `--svg-glob` SVGs, or else the ones at `svg_paths`.
+    pub(crate) fn svg_grid(&self, svg_paths: &[PathBuf]) -> Result<Vec<Dom>, BenchError> {
+        if !self.svg_grid.is_empty() {
+            return Ok(self.svg_grid.clone());
+        }
+        svg_paths
+            .iter()
+            .map(|svg_path| load_svg(svg_path))
+            .collect()
+    }
+
+    /// The cached or streamed SVG, or else the one at `svg_path`.
+    pub(crate) fn svg(&self, svg_path: Option<&Path>) -> Result<Dom, BenchError> {
+        match (&self.svg, svg_path) {
+            (Some(svg), _) => Ok(svg.clone()),
+            (None, Some(svg_path)) => load_svg(svg_path),
+            (None, None) => Err("no SVG was streamed".into()),
+        }
+    }
+}
+
+fn load_image(raster_path: &Path) -> Result<Image, BenchError> {
+    debug!("decoding image {}...", raster_path.display());
+    let bitmap_data =
+        data_from_file_path(raster_path).map_err(|error| BenchError::io(raster_path, error))?;
+    Image::from_encoded(bitmap_data)
+        .ok_or_else(|| BenchError::DecodeFailed(raster_path.display().to_string()))
+}
+
+/// Reads the SVG from stdin or `--svg-url` the first time any thread asks for it.
+fn streamed_svg_bytes(config: &BenchmarkConfig) -> Result<&'static [u8], BenchError> {
+    if let Some(svg_data) = STREAMED_SVG.get() {
+        return Ok(svg_data);
+    }
+    let svg_data = match &config.svg_url {
+        Some(url) => fetch_url(url)?,
+        None => {
+            debug!("reading SVG from stdin...");
+            let mut svg_data = vec![];
+            std::io::stdin()
+                .read_to_end(&mut svg_data)
+                .map_err(|error| BenchError::io(Path::new("<stdin>"), error))?;
+            svg_data
+        }
+    };
+    Ok(STREAMED_SVG.get_or_init(|| svg_data))
+}
+
+#[cfg(feature = "reqwest")]
+fn fetch_url(url: &str) -> Result<Vec<u8>, BenchError> {
+    debug!("fetching SVG {}...", url);
+    let failed = |error: reqwest::Error| format!("could not fetch {}: {}", url, error);
+    let response = reqwest::blocking::get(url)
+        .and_then(|response| response.error_for_status())
+        .map_err(failed)?;
+    let bytes = response.bytes().map_err(failed)?;
+    Ok(bytes.to_vec())
+}
+
+#[cfg(not(feature = "reqwest"))]
+fn fetch_url(url: &str) -> Result<Vec<u8>, BenchError> {
+    Err(format!("cannot fetch {}: built without the reqwest feature", url).into())
+}
+
+pub(crate) fn load_svg(svg_path: &Path) -> Result<Dom, BenchError> {
+    debug!("parsing SVG {}...", svg_path.display());
+    let svg_data =
+        bytes_from_file_path(svg_path).map_err(|error| BenchError::io(svg_path, error))?;
+    Dom::from_bytes(&svg_data).map_err(|_| BenchError::DecodeFailed(svg_path.display().to_string()))
+}
@@ -0,0 +1,146 @@
+use crate::error::BenchError;
+use crate::render::{check_file_exists, data_from_file_path, surface_pixels};
+use skia_safe::image::CachingHint;
+use skia_safe::{AlphaType, ColorType, Data, EncodedImageFormat, Image, ImageInfo, Surface};
+use std::path::Path;
+
+struct PixelDiff {
+    max_channel_delta: u8,
+    differing_fraction: f64,
+}
+
+impl PixelDiff {
+    fn between(actual: &[u8], expected: &[u8]) -> Self {
+        let mut max_channel_delta = 0;
+        let mut differing = 0;
+        let mut total = 0;
+        for (actual, expected) in actual.chunks(4).zip(expected.chunks(4)) {
+            let delta = actual
+                .iter()
+                .zip(expected)
+                .map(|(a, b)| a.abs_diff(*b))
+                .max()
+                .unwrap_or(0);
+            max_channel_delta = max_channel_delta.max(delta);
+            if delta > 0 {
+                differing += 1;
+            }
+            total += 1;
+        }
+        PixelDiff {
+            max_channel_delta,
+            differing_fraction: if total > 0 {
+                differing as f64 / total as f64
+            } else {
+                0.0
+            },
+        }
+    }
+}
+
+pub(crate) fn compare_with_baseline(
+    surface: &mut Surface,
+    baseline_path: &Path,
+    tolerance: f64,
+) -> Result<(), BenchError> {
+    let actual = surface_pixels(surface).ok_or("could not read surface pixels")?;
+    let expected = baseline_pixels(baseline_path, &surface.image_info())?;
+    let diff = PixelDiff::between(&actual, &expected);
+    eprintln!(
+        "baseline diff: max channel delta {}, {:.4}% of pixels differ",
+        diff.max_channel_delta,
+        diff.differing_fraction * 100.0
+    );
+    if diff.differing_fraction > tolerance {
+        return Err(format!(
+            "output differs from baseline in {:.4}% of pixels, tolerance is {:.4}%",
+            diff.differing_fraction * 100.0,
+            tolerance * 100.0
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Summarizes the pixel differences from a reference render, optionally writing them as an image.
+pub(crate) fn diff_with_reference(
+    surface: &mut Surface,
+    reference_path: &Path,
+    diff_image: Option<&Path>,
+) -> Result<String, BenchError> {
+    let image_info = ImageInfo::new(
+        surface.image_info().dimensions(),
+        ColorType::RGBA8888,
+        AlphaType::Unpremul,
+        None,
+    );
+    let row_bytes = image_info.min_row_bytes();
+    let mut actual = vec![0; row_bytes * image_info.height() as usize];
+    if !surface.read_pixels(&image_info, &mut actual, row_bytes, (0, 0)) {
+        return Err("could not read surface pixels".into());
+    }
+    let expected = baseline_pixels(reference_path, &image_info)?;
+    let diff = PixelDiff::between(&actual, &expected);
+    let summary = format!(
+        "reference diff: max channel delta {}, {:.4}% of pixels differ",
+        diff.max_channel_delta,
+        diff.differing_fraction * 100.0
+    );
+    if let Some(diff_image) = diff_image {
+        let pixels = highlight_differences(&actual, &expected);
+        let encoded = Image::from_raster_data(&image_info, Data::new_copy(&pixels), row_bytes)
+            .and_then(|image| image.encode(None, EncodedImageFormat::PNG, None))
+            .ok_or_else(|| BenchError::EncodeFailed("the diff image".to_string()))?;
+        std::fs::write(diff_image, encoded.as_bytes())
+            .map_err(|error| BenchError::io(diff_image, error))?;
+    }
+    Ok(summary)
+}
+
+/// Paints differing RGBA pixels red and fades matching ones, so the differences stand out.
+fn highlight_differences(actual: &[u8], expected: &[u8]) -> Vec<u8> {
+    actual
+        .chunks(4)
+        .zip(expected.chunks(4))
+        .flat_map(|(actual, expected)| {
+            if actual == expected {
+                let faded = |channel: u8| 192 + channel / 4;
+                [faded(actual[0]), faded(actual[1]), faded(actual[2]), 255]
+            } else {
+                [255, 0, 0, 255]
+            }
+        })
+        .collect()
+}
+
+/// Decodes an image and reads its pixels back in the layout described by `image_info`.
+fn baseline_pixels(baseline_path: &Path, image_info: &ImageInfo) -> Result<Vec<u8>, BenchError> {
+    let baseline_path = check_file_exists(baseline_path.to_path_buf())?;
+    let data = data_from_file_path(&baseline_path)
+        .map_err(|error| BenchError::io(&baseline_path, error))?;
+    let image = Image::from_encoded(data)
+        .ok_or_else(|| BenchError::DecodeFailed(baseline_path.display().to_string()))?;
+    if image.dimensions() != image_info.dimensions() {
+        return Err(format!(
+            "{} is {}x{} but the output is {}x{}",
+            baseline_path.display(),
+            image.width(),
+            image.height(),
+            image_info.width(),
+            image_info.height()
+        )
+        .into());
+    }
+    let row_bytes = image_info.min_row_bytes();
+    let mut pixels = vec![0; row_bytes * image_info.height() as usize];
+    if !image.read_pixels(
+        image_info,
+        &mut pixels,
+        row_bytes,
+        (0, 0),
+        CachingHint::Allow,
+    ) {
+        return Err(format!("could not read pixels of {}", baseline_path.display()).into());
+    }
+    Ok(pixels)
+}
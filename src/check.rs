@@ -0,0 +1,138 @@
+use crate::config::BenchmarkConfig;
+use crate::error::BenchError;
+use crate::render::{
+    bytes_from_file_path, check_file_exists, data_from_file_path, find_fonts, find_svgs,
+};
+use skia_safe::svg::Dom;
+use skia_safe::{Image, Path as SkPath, Typeface};
+use std::path::{Path, PathBuf};
+
+/// Validates that every input needed by the enabled stages exists and can be parsed.
+pub fn check_inputs(config: &BenchmarkConfig) -> Result<(), BenchError> {
+    let config = &config.with_default_stages();
+    let working_path = &config.dir_path;
+    let mut checks: Vec<(PathBuf, fn(&Path) -> Result<(), String>)> = vec![];
+    if config.draw_path {
+        match &config.path_file {
+            Some(path_file) => checks.push((working_path.join(path_file), check_path_list_file)),
+            None => checks.push((working_path.join("path.txt"), check_path_file)),
+        }
+    }
+    if config.draw_raster
+        || config.decode_only
+        || config.gen_mipmaps
+        || config.blur_sigma.is_some()
+        || !config.filter_chain.is_empty()
+    {
+        checks.push((working_path.join(&config.raster_file), check_raster_file));
+    }
+    if config.draw_text {
+        checks.push((working_path.join(&config.font_file), check_font_file));
+        if let Some(fallback_font) = &config.fallback_font {
+            checks.push((working_path.join(fallback_font), check_font_file));
+        }
+    }
+    if config.preset.is_some() {
+        checks.push((working_path.join(&config.raster_file), check_raster_file));
+        checks.push((working_path.join(&config.font_file), check_font_file));
+    }
+    if config.path_outline {
+        checks.push((working_path.join("path.txt"), check_path_file));
+    }
+    if let Some(fonts_dir) = &config.fonts_dir {
+        for font_path in find_fonts(&working_path.join(fonts_dir))? {
+            checks.push((font_path, check_font_file));
+        }
+    }
+    if config.simple_text {
+        checks.push((working_path.join(&config.font_file), check_font_file));
+    }
+    if config.text_on_path {
+        checks.push((working_path.join("path.txt"), check_path_file));
+        checks.push((working_path.join(&config.font_file), check_font_file));
+    }
+    if config.draw_svg {
+        match &config.svg_glob {
+            Some(pattern) => {
+                for svg_path in find_svgs(working_path, pattern)? {
+                    checks.push((svg_path, check_svg_file));
+                }
+            }
+            None if config.svg_from_stream() => {
+                println!("skipped: streamed SVG (validated when the benchmark loads it)")
+            }
+            None => checks.push((working_path.join(&config.svg_file), check_svg_file)),
+        }
+    }
+
+    let mut failed = 0;
+    for (path, check) in &checks {
+        let checked = check_file_exists(path.clone())
+            .map_err(|error| error.to_string())
+            .and_then(|path| check(&path));
+        match checked {
+            Ok(()) => println!("ok: {}", path.display()),
+            Err(message) => {
+                failed += 1;
+                println!("failed: {} ({})", path.display(), message);
+            }
+        }
+    }
+    println!(
+        "{} of {} input file(s) passed",
+        checks.len() - failed,
+        checks.len()
+    );
+    if failed > 0 {
+        return Err(format!("{} input file(s) failed validation", failed).into());
+    }
+    Ok(())
+}
+
+fn check_path_file(path: &Path) -> Result<(), String> {
+    let path_def = std::fs::read_to_string(path).map_err(|error| error.to_string())?;
+    SkPath::from_svg(path_def)
+        .map(|_| ())
+        .ok_or_else(|| "not a valid SVG path".to_string())
+}
+
+fn check_path_list_file(path: &Path) -> Result<(), String> {
+    let path_defs = std::fs::read_to_string(path).map_err(|error| error.to_string())?;
+    let lines: Vec<&str> = path_defs
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .collect();
+    let failed = lines
+        .iter()
+        .filter(|line| SkPath::from_svg(line).is_none())
+        .count();
+    if lines.is_empty() || failed > 0 {
+        return Err(format!(
+            "{} of {} path(s) are not valid SVG paths",
+            failed,
+            lines.len()
+        ));
+    }
+    Ok(())
+}
+
+fn check_raster_file(path: &Path) -> Result<(), String> {
+    let data = data_from_file_path(path).map_err(|error| error.to_string())?;
+    Image::from_encoded(data)
+        .map(|_| ())
+        .ok_or_else(|| "could not decode image".to_string())
+}
+
+fn check_font_file(path: &Path) -> Result<(), String> {
+    let data = data_from_file_path(path).map_err(|error| error.to_string())?;
+    Typeface::from_data(data, None)
+        .map(|_| ())
+        .ok_or_else(|| "could not load typeface".to_string())
+}
+
+fn check_svg_file(path: &Path) -> Result<(), String> {
+    let svg_data = bytes_from_file_path(path).map_err(|error| error.to_string())?;
+    Dom::from_bytes(&svg_data)
+        .map(|_| ())
+        .map_err(|_| "could not parse SVG".to_string())
+}
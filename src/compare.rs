@@ -0,0 +1,124 @@
+use crate::error::BenchError;
+use crate::timings::{format_millis, StageTimings};
+use std::fmt;
+use std::path::Path;
+use std::time::Duration;
+
+/// Mean duration of one stage in two `--json` reports.
+pub struct StageDelta {
+    pub stage: &'static str,
+    pub before: Duration,
+    pub after: Duration,
+}
+
+impl StageDelta {
+    /// Change from `before` to `after` in percent; positive means slower.
+    pub fn percent(&self) -> f64 {
+        (self.after.as_secs_f64() / self.before.as_secs_f64() - 1.0) * 100.0
+    }
+}
+
+pub struct Comparison {
+    pub stages: Vec<StageDelta>,
+    /// Percent slowdown above which a stage is flagged as a regression.
+    pub threshold: f64,
+}
+
+/// Compares the per-stage means of two reports written with `--json`.
+pub fn compare_reports(
+    before: &Path,
+    after: &Path,
+    threshold: f64,
+) -> Result<Comparison, BenchError> {
+    let before_records = load_records(before)?;
+    let after_records = load_records(after)?;
+    let mut names: Vec<&'static str> = StageTimings::default()
+        .stages()
+        .iter()
+        .map(|(name, _)| *name)
+        .collect();
+    names.push("render");
+    let stages = names
+        .into_iter()
+        .filter_map(|stage| {
+            let key = format!("{}_ns", stage);
+            let before = mean_of(&before_records, &key)?;
+            let after = mean_of(&after_records, &key)?;
+            (!before.is_zero() || !after.is_zero()).then_some(StageDelta {
+                stage,
+                before,
+                after,
+            })
+        })
+        .collect();
+    Ok(Comparison { stages, threshold })
+}
+
+/// Reads the measured records of a report, accepting the older bare-array layout as well.
+///
+/// Records before `--measure-from` are dropped; older reports without the `measured` field keep all.
+fn load_records(path: &Path) -> Result<Vec<serde_json::Value>, BenchError> {
+    let text = std::fs::read_to_string(path).map_err(|error| BenchError::io(path, error))?;
+    let report: serde_json::Value = serde_json::from_str(&text).map_err(|error| {
+        BenchError::DecodeFailed(format!("report {}: {}", path.display(), error))
+    })?;
+    let records = match report {
+        serde_json::Value::Array(records) => records,
+        serde_json::Value::Object(mut fields) => match fields.remove("records") {
+            Some(serde_json::Value::Array(records)) => records,
+            _ => return Err(format!("no records in report {}", path.display()).into()),
+        },
+        _ => {
+            return Err(BenchError::DecodeFailed(format!(
+                "report {}",
+                path.display()
+            )))
+        }
+    };
+    Ok(records
+        .into_iter()
+        .filter(|record| record.get("measured").and_then(serde_json::Value::as_bool) != Some(false))
+        .collect())
+}
+
+fn mean_of(records: &[serde_json::Value], key: &str) -> Option<Duration> {
+    let samples: Vec<u64> = records
+        .iter()
+        .filter_map(|record| record.get(key)?.as_u64())
+        .collect();
+    if samples.is_empty() {
+        return None;
+    }
+    let total: u64 = samples.iter().sum();
+    Some(Duration::from_nanos(total / samples.len() as u64))
+}
+
+impl fmt::Display for Comparison {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:<10}{:>12}{:>12}{:>10}",
+            "stage", "before", "after", "delta"
+        )?;
+        for delta in &self.stages {
+            let percent = delta.percent();
+            let verdict = if percent > self.threshold {
+                "  REGRESSION"
+            } else if percent < -self.threshold {
+                "  improved"
+            } else {
+                ""
+            };
+            write!(
+                f,
+                "\n{:<10}{:>12}{:>12}{:>9.1}%{}",
+                delta.stage,
+                format_millis(delta.before),
+                format_millis(delta.after),
+                percent,
+                verdict
+            )?;
+        }
+        Ok(())
+    }
+}
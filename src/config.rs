@@ -0,0 +1,849 @@
+use clap::{Args, Command, FromArgMatches, ValueEnum};
+use skia_safe::color_space::{named_gamut, named_transfer_fn};
+use skia_safe::paint::{Cap, Join};
+use skia_safe::textlayout::TextShadow;
+use skia_safe::{
+    color_filters, image_filters, BlendMode, Color, ColorFilter, ColorMatrix, ColorSpace,
+    CubicResampler, EncodedImageFormat, FilterMode, ImageFilter, MipmapMode, Rect, SamplingOptions,
+};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+pub const CANVAS_SIZE: i32 = 512;
+
+/// Benchmark settings, mirroring the command-line flags.
+#[derive(Args, Clone, Debug)]
+pub struct BenchmarkConfig {
+    #[arg(long = "dir")]
+    pub dir_path: std::path::PathBuf,
+    /// Directory for rendered output; defaults to `--dir`.
+    #[arg(long = "out-dir")]
+    pub out_dir: Option<PathBuf>,
+    #[arg(long = "raster-file", default_value = "mars.jpg")]
+    pub raster_file: PathBuf,
+    #[arg(long = "font-file", default_value = "Adigiana_Ultra.ttf")]
+    pub font_file: PathBuf,
+    /// SVG drawn by the `svg` stage; `-` reads it from stdin.
+    #[arg(long = "svg-file", default_value = "pinocchio.svg")]
+    pub svg_file: PathBuf,
+    /// Fetch the SVG over HTTP instead of reading `--svg-file` (requires the `reqwest` feature).
+    #[arg(long = "svg-url", conflicts_with_all = ["svg_file", "svg_glob"])]
+    pub svg_url: Option<String>,
+    #[arg(long = "loop", default_value_t = 1)]
+    pub loop_count: u16,
+    /// Keep running iterations until this many seconds have passed, instead of `--loop`.
+    #[arg(long = "duration", conflicts_with = "loop_count", value_parser = parse_seconds)]
+    pub duration_secs: Option<f64>,
+    /// Render this many animation frames, with the SVG rotating and the text fading in, saving each.
+    #[arg(long = "frames", conflicts_with_all = ["loop_count", "duration_secs"], value_parser = clap::value_parser!(u32).range(1..))]
+    pub frames: Option<u32>,
+    /// Stop after the iteration during which this many seconds have passed, whatever `--loop` says.
+    #[arg(long = "timeout", value_parser = parse_seconds)]
+    pub timeout_secs: Option<f64>,
+    #[arg(long = "warmup", default_value_t = 0)]
+    pub warmup_count: u16,
+    /// Run but exclude iterations before this index from the statistics; they still count towards `--loop`.
+    #[arg(long = "measure-from", default_value_t = 0)]
+    pub measure_from: u16,
+    #[arg(long = "threads", default_value_t = 1, value_parser = clap::value_parser!(u16).range(1..))]
+    pub threads: u16,
+    /// Pin the benchmark thread to this CPU core; with `--threads`, thread `i` goes to core `n + i`.
+    #[arg(long = "pin-core")]
+    pub pin_core: Option<usize>,
+    #[arg(long = "path")]
+    pub draw_path: bool,
+    /// File of newline-separated SVG path strings to draw in a grid instead of `path.txt`.
+    #[arg(long = "path-file")]
+    pub path_file: Option<PathBuf>,
+    #[arg(long = "gradient")]
+    pub gradient: bool,
+    #[arg(long = "stroke")]
+    pub stroke_width: Option<f32>,
+    /// Convert the path to its stroke outline (`--stroke` wide, 4 by default) and fill that, as the `outline` stage.
+    #[arg(long = "path-outline")]
+    pub path_outline: bool,
+    /// Dash the stroked path with `on,off` interval lengths.
+    #[arg(long = "dash", value_parser = parse_dash, requires = "stroke_width")]
+    pub dash: Option<(f32, f32)>,
+    #[arg(long = "stroke-cap", value_enum, default_value_t = StrokeCap::Butt)]
+    pub stroke_cap: StrokeCap,
+    #[arg(long = "stroke-join", value_enum, default_value_t = StrokeJoin::Miter)]
+    pub stroke_join: StrokeJoin,
+    #[arg(long = "random-shapes")]
+    pub random_shapes: Option<u32>,
+    #[arg(long = "seed", default_value_t = 0)]
+    pub seed: u64,
+    /// Draw rounded rectangles and ovals, the common UI primitives, as the `shapes` stage.
+    #[arg(long = "draw-shapes")]
+    pub draw_shapes: bool,
+    #[arg(long = "round-rect-count", default_value_t = 100)]
+    pub round_rect_count: u32,
+    #[arg(long = "oval-count", default_value_t = 100)]
+    pub oval_count: u32,
+    /// Draw this many seeded random triangles with per-vertex colors via `draw_vertices`.
+    #[arg(long = "mesh", value_parser = clap::value_parser!(u32).range(1..))]
+    pub mesh: Option<u32>,
+    /// Draw a composed scene mixing the primitives, measured as the `preset` stage.
+    #[arg(long = "preset", value_enum)]
+    pub preset: Option<Preset>,
+    /// Draw a single primitive `--micro-count` times to measure per-call overhead.
+    #[arg(long = "micro", value_enum)]
+    pub micro: Option<MicroPrimitive>,
+    #[arg(long = "micro-count", default_value_t = 10_000, value_parser = clap::value_parser!(u32).range(1..))]
+    pub micro_count: u32,
+    /// Fill the canvas with one large anti-aliased circle `--fill-count` times and report the fill
+    /// rate, as the `fill` stage.
+    #[arg(long = "fill-rate")]
+    pub fill_rate: bool,
+    #[arg(long = "fill-count", default_value_t = 100, value_parser = clap::value_parser!(u32).range(1..))]
+    pub fill_count: u32,
+    #[arg(long = "raster")]
+    pub draw_raster: bool,
+    /// Tile the raster image across the canvas as `<rows>x<cols>` cells.
+    #[arg(long = "raster-grid", value_parser = parse_grid)]
+    pub raster_grid: Option<(u32, u32)>,
+    /// Time decoding the raster image into a raster-backed image, without drawing it.
+    #[arg(long = "decode-only")]
+    pub decode_only: bool,
+    /// Build mipmaps for the decoded raster (`mipbuild`), then draw it heavily downscaled (`mipdraw`).
+    #[arg(long = "gen-mipmaps")]
+    pub gen_mipmaps: bool,
+    #[arg(long = "sampling", value_enum, default_value_t = Sampling::Mipmap)]
+    pub sampling: Sampling,
+    #[arg(long = "blur")]
+    pub blur_sigma: Option<f32>,
+    /// Color filter applied to the paint of the raster stage.
+    #[arg(long = "color-filter", value_enum)]
+    pub color_filter: Option<ColorFilterMode>,
+    /// Draw the raster through these image filters, applied in order, as the `filters` stage.
+    #[arg(long = "filter-chain", value_enum, value_delimiter = ',')]
+    pub filter_chain: Vec<Filter>,
+    #[arg(long = "text")]
+    pub draw_text: bool,
+    /// Lay out the text right-to-left, with Arabic and Hebrew runs mixed in.
+    #[arg(long = "rtl")]
+    pub rtl: bool,
+    /// Extra space between glyphs of the sample paragraph, in pixels.
+    #[arg(long = "letter-spacing")]
+    pub letter_spacing: Option<f32>,
+    /// Extra space between words of the sample paragraph, in pixels.
+    #[arg(long = "word-spacing")]
+    pub word_spacing: Option<f32>,
+    #[arg(long = "text-width", default_value_t = 225.0)]
+    pub text_width: f32,
+    /// Re-lay out the paragraph at this many widths between half and one and a half `--text-width`.
+    #[arg(long = "text-reflow", value_parser = clap::value_parser!(u32).range(1..))]
+    pub text_reflow: Option<u32>,
+    /// Draw the text with a drop shadow, configured by the `--shadow-*` flags.
+    #[arg(long = "text-shadow")]
+    pub text_shadow: bool,
+    #[arg(long = "shadow-color", default_value = "#00000080", value_parser = parse_color)]
+    pub shadow_color: Color,
+    #[arg(long = "shadow-blur", default_value_t = 2.0)]
+    pub shadow_blur: f64,
+    /// Shadow offset as `x,y`.
+    #[arg(long = "shadow-offset", default_value = "2,2", value_parser = parse_offset)]
+    pub shadow_offset: (f32, f32),
+    /// Draw the sample text line by line with `draw_str`, bypassing paragraph layout, as the `simpletext` stage.
+    #[arg(long = "simple-text")]
+    pub simple_text: bool,
+    /// Draw the sample text along the contours of the Pinocchio path as its own `textpath` stage.
+    #[arg(long = "text-on-path")]
+    pub text_on_path: bool,
+    /// Time the paragraph once right after purging the glyph cache and once with it warm.
+    #[arg(long = "glyph-cache-stats")]
+    pub glyph_cache_stats: bool,
+    #[arg(long = "text-count", default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+    pub text_count: u32,
+    /// Register every `.ttf` and `.otf` in this directory with a font provider, as the `fonts` stage.
+    #[arg(long = "fonts-dir")]
+    pub fonts_dir: Option<PathBuf>,
+    /// Font registered as a fallback family for the CJK and emoji text in the `fallback` stage.
+    #[arg(long = "fallback-font")]
+    pub fallback_font: Option<PathBuf>,
+    #[arg(long = "svg")]
+    pub draw_svg: bool,
+    #[arg(long = "svg-glob")]
+    pub svg_glob: Option<String>,
+    #[arg(long = "save")]
+    pub save: bool,
+    /// Save every iteration as `output-rust-{i}` instead of overwriting one file.
+    #[arg(long = "save-each")]
+    pub save_each: bool,
+    /// Output file name with `{i}` (zero-padded iteration) and `{scale}` placeholders.
+    #[arg(long = "save-template")]
+    pub save_template: Option<String>,
+    /// Save `after-<stage>.png` after each stage to show how the scene builds up.
+    #[arg(long = "snapshot-stages")]
+    pub snapshot_stages: bool,
+    /// Also encode every iteration to PNG, JPEG and WebP, timing each encoder and writing each file.
+    #[arg(long = "encode-all")]
+    pub encode_all: bool,
+    /// Decode the saved PNG and fail unless it embeds a profile for `--colorspace`.
+    #[arg(long = "verify-icc")]
+    pub verify_icc: bool,
+    /// Leave the output file alone when its contents hash the same as the new encoding.
+    #[arg(long = "save-if-changed")]
+    pub save_if_changed: bool,
+    #[arg(long = "measure-save")]
+    pub measure_save: bool,
+    /// Only create, clear and save the surface, to measure the fixed overhead of an iteration.
+    #[arg(
+        long = "baseline-only",
+        conflicts_with_all = ["draw_path", "path_outline", "random_shapes", "micro", "fill_rate", "preset", "draw_shapes", "mesh", "draw_raster", "decode_only", "gen_mipmaps", "blur_sigma", "filter_chain", "draw_text", "text_on_path", "simple_text", "fonts_dir", "draw_svg"]
+    )]
+    pub baseline_only: bool,
+    /// Only create a fresh surface each iteration, without clearing, drawing or saving it.
+    #[arg(
+        long = "measure-surface-create",
+        conflicts_with_all = ["baseline_only", "no_clear", "draw_path", "path_outline", "random_shapes", "micro", "fill_rate", "preset", "draw_shapes", "mesh", "draw_raster", "decode_only", "gen_mipmaps", "blur_sigma", "filter_chain", "draw_text", "text_on_path", "simple_text", "fonts_dir", "draw_svg"]
+    )]
+    pub measure_surface_create: bool,
+    #[arg(long = "format", value_enum, default_value_t = OutputFormat::Png)]
+    pub format: OutputFormat,
+    #[arg(long = "quality", value_parser = clap::value_parser!(u8).range(0..=100))]
+    pub quality: Option<u8>,
+    /// zlib compression level for PNG output; Skia's default is used when unset.
+    #[arg(long = "png-level", value_parser = clap::value_parser!(u8).range(0..=9))]
+    pub png_level: Option<u8>,
+    /// Write the raw premultiplied RGBA pixels to this file, with a `.meta` file alongside.
+    #[arg(long = "raw")]
+    pub raw_path: Option<PathBuf>,
+    #[arg(long = "scale", default_value_t = 1.0, value_parser = parse_scale)]
+    pub scale: f32,
+    /// Run the whole benchmark at each scale from `start` to `end` by `step`, given as `start,end,step`.
+    #[arg(long = "scale-sweep", value_parser = parse_sweep, conflicts_with = "scale")]
+    pub scale_sweep: Option<(f32, f32, f32)>,
+    #[arg(long = "width", default_value_t = CANVAS_SIZE, value_parser = clap::value_parser!(i32).range(1..))]
+    pub width: i32,
+    #[arg(long = "height", default_value_t = CANVAS_SIZE, value_parser = clap::value_parser!(i32).range(1..))]
+    pub height: i32,
+    #[arg(long = "colorspace", value_enum, default_value_t = SurfaceColorSpace::Srgb)]
+    pub colorspace: SurfaceColorSpace,
+    /// Blend mode for the shared paint used by the path, shape and raster stages.
+    #[arg(long = "blend", value_enum)]
+    pub blend: Option<Blend>,
+    /// Clip every stage to the rectangle `x,y,w,h`, in canvas coordinates.
+    #[arg(long = "clip", value_parser = parse_clip)]
+    pub clip: Option<Rect>,
+    /// Clip every stage to the outline from `path.txt`.
+    #[arg(long = "clip-path")]
+    pub clip_path: bool,
+    /// Rotate the canvas around its center by this many degrees before drawing.
+    #[arg(long = "rotate", default_value_t = 0.0)]
+    pub rotate: f32,
+    /// Redraw the whole scene this many times, each pass rotated a further 360/n degrees.
+    #[arg(long = "transform-count", default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+    pub transform_count: u32,
+    /// Background color to clear the surface with, as `#RRGGBB` or `#RRGGBBAA`.
+    #[arg(long = "bg", default_value = "#FFFFFF", value_parser = parse_color)]
+    pub background: Color,
+    /// Draw a reference grid every `--grid-spacing` canvas units over the finished scene; not timed.
+    #[arg(long = "grid")]
+    pub grid: bool,
+    #[arg(long = "grid-spacing", default_value_t = 32.0, value_parser = parse_scale)]
+    pub grid_spacing: f32,
+    /// Fill the background with a checkerboard shader of `--checker-size` pixel squares over `--bg`.
+    #[arg(long = "checkerboard")]
+    pub checkerboard: bool,
+    #[arg(long = "checker-size", default_value_t = 16, value_parser = clap::value_parser!(i32).range(1..))]
+    pub checker_size: i32,
+    /// Decode the image, font and SVG once before the loop instead of on every iteration.
+    #[arg(long = "cache-assets")]
+    pub cache_assets: bool,
+    /// Request an unpremultiplied surface; Skia renders into premultiplied surfaces only, so this
+    /// falls back with a warning where unsupported, and blending results may differ where it works.
+    #[arg(long = "unpremul")]
+    pub unpremul: bool,
+    /// Clear to transparent instead of `--bg` so saved PNGs keep an alpha channel.
+    #[arg(long = "transparent")]
+    pub transparent: bool,
+    /// Keep drawing onto the first iteration's surface without clearing it.
+    #[arg(long = "no-clear")]
+    pub no_clear: bool,
+    /// Allocate the surface once and only clear it between iterations, like a long-running app.
+    #[arg(long = "reuse-surface", conflicts_with_all = ["no_clear", "measure_surface_create"])]
+    pub reuse_surface: bool,
+    /// After a full first frame, only clear and redraw the rectangle `x,y,w,h` (canvas coordinates)
+    /// on the kept surface, and report the speedup over the full redraw.
+    #[arg(
+        long = "dirty-rect",
+        value_parser = parse_clip,
+        conflicts_with_all = ["no_clear", "reuse_surface", "tile", "measure_surface_create"]
+    )]
+    pub dirty_rect: Option<Rect>,
+    /// Render the scene as this many horizontal tiles and stitch them into the output.
+    #[arg(long = "tile", value_parser = clap::value_parser!(u32).range(1..))]
+    pub tile: Option<u32>,
+    #[arg(long = "gpu")]
+    pub gpu: bool,
+    /// With `--gpu`, time uploading the raster image as a texture and reading the rendered pixels back.
+    #[arg(long = "gpu-readback", requires = "gpu")]
+    pub gpu_readback: bool,
+    /// MSAA sample count for the GPU render target; unsupported counts use the nearest supported one.
+    #[arg(long = "msaa", value_parser = clap::value_parser!(u32).range(1..))]
+    pub msaa: Option<u32>,
+    #[arg(long = "picture")]
+    pub picture: bool,
+    #[arg(long = "pdf")]
+    pub pdf_path: Option<PathBuf>,
+    /// Serialize the enabled stages through Skia's SVG backend each iteration and write the last result.
+    #[arg(long = "svg-out")]
+    pub svg_out: Option<PathBuf>,
+    /// Disable anti-aliasing on the shared paint used by the path and raster stages.
+    /// Text and SVG rendering manage their own anti-aliasing and are not affected.
+    #[arg(long = "no-aa")]
+    pub no_aa: bool,
+    #[arg(long = "hash")]
+    pub hash: bool,
+    #[arg(long = "baseline")]
+    pub baseline_path: Option<PathBuf>,
+    /// Report pixel differences from a reference PNG rendered by another Skia binding.
+    #[arg(long = "diff-reference")]
+    pub diff_reference: Option<PathBuf>,
+    /// Write an image highlighting the pixels that differ from `--diff-reference` in red.
+    #[arg(long = "diff-image", requires = "diff_reference")]
+    pub diff_image: Option<PathBuf>,
+    /// Fraction of pixels (0.0-1.0) allowed to differ from `--baseline` before failing.
+    #[arg(long = "tolerance", default_value_t = 0.0)]
+    pub tolerance: f64,
+    /// Print an ASCII histogram of per-iteration render times after the summary.
+    #[arg(long = "histogram")]
+    pub histogram: bool,
+    #[arg(long = "histogram-bins", default_value_t = 10, value_parser = clap::value_parser!(u16).range(1..))]
+    pub histogram_bins: u16,
+    #[arg(long = "check")]
+    pub check: bool,
+    /// Show a progress bar while iterating; ignored when stdout is not a terminal.
+    #[arg(long = "progress")]
+    pub progress: bool,
+    /// Only print errors and warnings; file outputs such as `--csv` and `--json` are still written.
+    #[arg(long = "quiet", conflicts_with = "verbose")]
+    pub quiet: bool,
+    /// Append every iteration's timings to a `timings` table in this SQLite database (requires the `rusqlite` feature).
+    #[arg(long = "sqlite")]
+    pub sqlite_path: Option<PathBuf>,
+    /// Stream one JSON object per iteration to stdout instead of the human-readable report.
+    #[arg(long = "ndjson", conflicts_with = "threads")]
+    pub ndjson: bool,
+    #[arg(long = "verbose")]
+    pub verbose: bool,
+    #[arg(long = "mem")]
+    pub mem: bool,
+    #[arg(long = "csv")]
+    pub csv_path: Option<PathBuf>,
+    #[arg(long = "json")]
+    pub json_path: Option<PathBuf>,
+    /// Write per-stage spans in Chrome Trace Event format, for chrome://tracing or Perfetto.
+    #[arg(long = "trace")]
+    pub trace_path: Option<PathBuf>,
+}
+
+impl BenchmarkConfig {
+    /// Creates a configuration with the command-line defaults that reads its inputs from `dir_path`.
+    pub fn new(dir_path: impl Into<PathBuf>) -> Self {
+        let matches = BenchmarkConfig::augment_args(Command::new("benchmark"))
+            .try_get_matches_from(["benchmark", "--dir", "."])
+            .expect("default arguments should parse");
+        let mut config =
+            BenchmarkConfig::from_arg_matches(&matches).expect("default arguments should parse");
+        config.dir_path = dir_path.into();
+        config
+    }
+
+    /// Whether the SVG comes from stdin or `--svg-url`, which can only be read once per run.
+    pub(crate) fn svg_from_stream(&self) -> bool {
+        self.svg_url.is_some() || self.svg_file == Path::new("-")
+    }
+
+    /// Whether another measured iteration should start, given how many ran since `start` and
+    /// when the `--timeout` clock began.
+    pub(crate) fn keep_running(
+        &self,
+        iterations: usize,
+        start: Instant,
+        timeout_start: Instant,
+    ) -> bool {
+        !self.timed_out(timeout_start) && self.wants_more(iterations, start)
+    }
+
+    /// Whether `--timeout` has passed since `start`.
+    pub(crate) fn timed_out(&self, start: Instant) -> bool {
+        self.timeout_secs.map_or(false, |secs| {
+            start.elapsed() >= Duration::from_secs_f64(secs)
+        })
+    }
+
+    /// Whether `--frames`, `--duration` or `--loop` asks for another iteration, ignoring `--timeout`.
+    pub(crate) fn wants_more(&self, iterations: usize, start: Instant) -> bool {
+        if let Some(frames) = self.frames {
+            return iterations < frames as usize;
+        }
+        match self.duration_secs {
+            Some(secs) => start.elapsed() < Duration::from_secs_f64(secs),
+            None => iterations < usize::from(self.loop_count),
+        }
+    }
+
+    pub(crate) fn clear_color(&self) -> Color {
+        if self.transparent {
+            Color::TRANSPARENT
+        } else {
+            self.background
+        }
+    }
+
+    pub(crate) fn text_shadow(&self) -> Option<TextShadow> {
+        self.text_shadow
+            .then(|| TextShadow::new(self.shadow_color, self.shadow_offset, self.shadow_blur))
+    }
+
+    /// Animation progress from 0.0 to 1.0 for `iteration` in `--frames` mode.
+    pub(crate) fn frame_progress(&self, iteration: usize) -> Option<f32> {
+        self.frames.map(|frames| {
+            if frames > 1 {
+                iteration as f32 / (frames - 1) as f32
+            } else {
+                1.0
+            }
+        })
+    }
+
+    /// Surface dimensions in pixels: the canvas size times `--scale`, rounded and at least 1x1.
+    pub fn surface_size(&self) -> (i32, i32) {
+        let scaled = |length: i32| ((length as f32 * self.scale).round() as i32).max(1);
+        (scaled(self.width), scaled(self.height))
+    }
+
+    pub fn output_dir(&self) -> &Path {
+        self.out_dir.as_deref().unwrap_or(&self.dir_path)
+    }
+
+    /// Path of the saved image for `iteration`, without the format extension.
+    pub(crate) fn output_stem(&self, iteration: usize) -> PathBuf {
+        let template = self.output_template();
+        let count = self.frames.unwrap_or(u32::from(self.loop_count));
+        let width = count.to_string().len().max(4);
+        let name = template
+            .replace("{i}", &format!("{:0width$}", iteration, width = width))
+            .replace("{scale}", &self.scale.to_string());
+        self.output_dir().join(name)
+    }
+
+    /// The `--save-template` file name, or the default one.
+    pub(crate) fn output_template(&self) -> &str {
+        match &self.save_template {
+            Some(template) => template.as_str(),
+            None if self.save_each || self.frames.is_some() => "output-rust-{i}",
+            None => "output-rust",
+        }
+    }
+
+    /// Enables every stage and saving when none of them was requested explicitly.
+    pub fn with_default_stages(&self) -> Self {
+        let mut config = self.clone();
+        if !(config.draw_path
+            || config.path_outline
+            || config.random_shapes.is_some()
+            || config.micro.is_some()
+            || config.fill_rate
+            || config.preset.is_some()
+            || config.draw_shapes
+            || config.mesh.is_some()
+            || config.draw_raster
+            || config.decode_only
+            || config.gen_mipmaps
+            || config.blur_sigma.is_some()
+            || !config.filter_chain.is_empty()
+            || config.draw_text
+            || config.text_on_path
+            || config.simple_text
+            || config.fonts_dir.is_some()
+            || config.draw_svg
+            || config.save
+            || config.baseline_only
+            || config.measure_surface_create)
+        {
+            config.draw_path = true;
+            config.draw_raster = true;
+            config.draw_text = true;
+            config.draw_svg = true;
+            config.save = true;
+        }
+        if config.frames.is_some() {
+            config.save = true;
+        }
+        if config.baseline_only {
+            config.save = true;
+            config.measure_save = true;
+        }
+        config
+    }
+}
+
+/// `stem` with `.<extension>` appended to its file name.
+///
+/// Unlike `Path::with_extension` this keeps a fractional `{scale}` in the stem intact.
+pub(crate) fn append_extension(stem: &Path, extension: &str) -> PathBuf {
+    let mut path = stem.as_os_str().to_owned();
+    path.push(".");
+    path.push(extension);
+    PathBuf::from(path)
+}
+
+fn parse_grid(value: &str) -> Result<(u32, u32), String> {
+    let invalid = || format!("expected <rows>x<cols>, got '{}'", value);
+    let (rows, cols) = value.split_once('x').ok_or_else(invalid)?;
+    let rows: u32 = rows.trim().parse().map_err(|_| invalid())?;
+    let cols: u32 = cols.trim().parse().map_err(|_| invalid())?;
+    if rows == 0 || cols == 0 {
+        return Err(invalid());
+    }
+    Ok((rows, cols))
+}
+
+fn parse_clip(value: &str) -> Result<Rect, String> {
+    let parts: Vec<f32> = value
+        .split(',')
+        .map(|part| part.trim().parse())
+        .collect::<Result<_, _>>()
+        .map_err(|_| format!("expected x,y,w,h, got '{}'", value))?;
+    match parts[..] {
+        [x, y, w, h] => Ok(Rect::from_xywh(x, y, w, h)),
+        _ => Err(format!("expected x,y,w,h, got '{}'", value)),
+    }
+}
+
+/// Parses a positive number of seconds that fits in a `Duration`.
+fn parse_seconds(value: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(secs) if secs > 0.0 && Duration::try_from_secs_f64(secs).is_ok() => Ok(secs),
+        _ => Err(format!(
+            "expected a positive number of seconds, got '{}'",
+            value
+        )),
+    }
+}
+
+fn parse_scale(value: &str) -> Result<f32, String> {
+    match value.parse::<f32>() {
+        Ok(scale) if scale.is_finite() && scale > 0.0 => Ok(scale),
+        _ => Err(format!("expected a positive number, got '{}'", value)),
+    }
+}
+
+fn parse_sweep(value: &str) -> Result<(f32, f32, f32), String> {
+    let invalid = || {
+        format!(
+            "expected <start>,<end>,<step> with 0 < start <= end, got '{}'",
+            value
+        )
+    };
+    let parts: Vec<&str> = value.split(',').collect();
+    let [start, end, step] = parts[..] else {
+        return Err(invalid());
+    };
+    let start = parse_scale(start.trim()).map_err(|_| invalid())?;
+    let end = parse_scale(end.trim()).map_err(|_| invalid())?;
+    let step = parse_scale(step.trim()).map_err(|_| invalid())?;
+    if start > end {
+        return Err(invalid());
+    }
+    Ok((start, end, step))
+}
+
+fn parse_dash(value: &str) -> Result<(f32, f32), String> {
+    let invalid = || format!("expected <on>,<off> with positive lengths, got '{}'", value);
+    let (on, off) = value.split_once(',').ok_or_else(invalid)?;
+    let on = parse_scale(on.trim()).map_err(|_| invalid())?;
+    let off = parse_scale(off.trim()).map_err(|_| invalid())?;
+    Ok((on, off))
+}
+
+fn parse_offset(value: &str) -> Result<(f32, f32), String> {
+    let invalid = || format!("expected x,y, got '{}'", value);
+    let (x, y) = value.split_once(',').ok_or_else(invalid)?;
+    let x = x.trim().parse().map_err(|_| invalid())?;
+    let y = y.trim().parse().map_err(|_| invalid())?;
+    Ok((x, y))
+}
+
+fn parse_color(value: &str) -> Result<Color, String> {
+    let invalid = || format!("expected #RRGGBB or #RRGGBBAA, got '{}'", value);
+    let hex = value.strip_prefix('#').ok_or_else(invalid)?;
+    if !(hex.len() == 6 || hex.len() == 8) {
+        return Err(invalid());
+    }
+    let channel = |index: usize| {
+        hex.get(index * 2..index * 2 + 2)
+            .and_then(|digits| u8::from_str_radix(digits, 16).ok())
+            .ok_or_else(invalid)
+    };
+    let alpha = if hex.len() == 8 { channel(3)? } else { 0xff };
+    Ok(Color::from_argb(
+        alpha,
+        channel(0)?,
+        channel(1)?,
+        channel(2)?,
+    ))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    Png,
+    Jpeg,
+    Webp,
+}
+
+/// Every output format, in the order `--encode-all` reports them.
+pub(crate) const ENCODED_FORMATS: [OutputFormat; 3] =
+    [OutputFormat::Png, OutputFormat::Jpeg, OutputFormat::Webp];
+
+impl OutputFormat {
+    pub(crate) fn encoded_format(self) -> EncodedImageFormat {
+        match self {
+            OutputFormat::Png => EncodedImageFormat::PNG,
+            OutputFormat::Jpeg => EncodedImageFormat::JPEG,
+            OutputFormat::Webp => EncodedImageFormat::WEBP,
+        }
+    }
+
+    pub(crate) fn extension(self) -> &'static str {
+        match self {
+            OutputFormat::Png => "png",
+            OutputFormat::Jpeg => "jpeg",
+            OutputFormat::Webp => "webp",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Sampling {
+    Nearest,
+    Linear,
+    Mipmap,
+    Cubic,
+}
+
+impl Sampling {
+    pub(crate) fn options(self) -> SamplingOptions {
+        match self {
+            Sampling::Nearest => SamplingOptions::new(FilterMode::Nearest, MipmapMode::None),
+            Sampling::Linear => SamplingOptions::new(FilterMode::Linear, MipmapMode::None),
+            Sampling::Mipmap => SamplingOptions::new(FilterMode::Linear, MipmapMode::Linear),
+            Sampling::Cubic => SamplingOptions::from(CubicResampler::mitchell()),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum SurfaceColorSpace {
+    Srgb,
+    DisplayP3,
+    Linear,
+}
+
+impl SurfaceColorSpace {
+    pub(crate) fn color_space(self) -> ColorSpace {
+        match self {
+            SurfaceColorSpace::Srgb => ColorSpace::new_srgb(),
+            SurfaceColorSpace::DisplayP3 => {
+                ColorSpace::new_rgb(&named_transfer_fn::SRGB, &named_gamut::DISPLAY_P3)
+            }
+            SurfaceColorSpace::Linear => ColorSpace::new_srgb_linear(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Preset {
+    /// A toolbar and a grid of cards with labels, plus one image thumbnail.
+    Ui,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum MicroPrimitive {
+    Circle,
+    Rect,
+    Line,
+    Text,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Blend {
+    Clear,
+    Src,
+    Dst,
+    SrcOver,
+    DstOver,
+    SrcIn,
+    DstIn,
+    SrcOut,
+    DstOut,
+    SrcAtop,
+    DstAtop,
+    Xor,
+    Plus,
+    Modulate,
+    Screen,
+    Overlay,
+    Darken,
+    Lighten,
+    ColorDodge,
+    ColorBurn,
+    HardLight,
+    SoftLight,
+    Difference,
+    Exclusion,
+    Multiply,
+    Hue,
+    Saturation,
+    Color,
+    Luminosity,
+}
+
+impl Blend {
+    pub(crate) fn mode(self) -> BlendMode {
+        match self {
+            Blend::Clear => BlendMode::Clear,
+            Blend::Src => BlendMode::Src,
+            Blend::Dst => BlendMode::Dst,
+            Blend::SrcOver => BlendMode::SrcOver,
+            Blend::DstOver => BlendMode::DstOver,
+            Blend::SrcIn => BlendMode::SrcIn,
+            Blend::DstIn => BlendMode::DstIn,
+            Blend::SrcOut => BlendMode::SrcOut,
+            Blend::DstOut => BlendMode::DstOut,
+            Blend::SrcAtop => BlendMode::SrcATop,
+            Blend::DstAtop => BlendMode::DstATop,
+            Blend::Xor => BlendMode::Xor,
+            Blend::Plus => BlendMode::Plus,
+            Blend::Modulate => BlendMode::Modulate,
+            Blend::Screen => BlendMode::Screen,
+            Blend::Overlay => BlendMode::Overlay,
+            Blend::Darken => BlendMode::Darken,
+            Blend::Lighten => BlendMode::Lighten,
+            Blend::ColorDodge => BlendMode::ColorDodge,
+            Blend::ColorBurn => BlendMode::ColorBurn,
+            Blend::HardLight => BlendMode::HardLight,
+            Blend::SoftLight => BlendMode::SoftLight,
+            Blend::Difference => BlendMode::Difference,
+            Blend::Exclusion => BlendMode::Exclusion,
+            Blend::Multiply => BlendMode::Multiply,
+            Blend::Hue => BlendMode::Hue,
+            Blend::Saturation => BlendMode::Saturation,
+            Blend::Color => BlendMode::Color,
+            Blend::Luminosity => BlendMode::Luminosity,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum StrokeCap {
+    Butt,
+    Round,
+    Square,
+}
+
+impl StrokeCap {
+    pub(crate) fn cap(self) -> Cap {
+        match self {
+            StrokeCap::Butt => Cap::Butt,
+            StrokeCap::Round => Cap::Round,
+            StrokeCap::Square => Cap::Square,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ColorFilterMode {
+    Grayscale,
+    Sepia,
+    Invert,
+}
+
+impl ColorFilterMode {
+    pub(crate) fn color_filter(self) -> Option<ColorFilter> {
+        #[rustfmt::skip]
+        let matrix = match self {
+            ColorFilterMode::Grayscale => ColorMatrix::new(
+                0.2126, 0.7152, 0.0722, 0.0, 0.0,
+                0.2126, 0.7152, 0.0722, 0.0, 0.0,
+                0.2126, 0.7152, 0.0722, 0.0, 0.0,
+                0.0, 0.0, 0.0, 1.0, 0.0,
+            ),
+            ColorFilterMode::Sepia => ColorMatrix::new(
+                0.393, 0.769, 0.189, 0.0, 0.0,
+                0.349, 0.686, 0.168, 0.0, 0.0,
+                0.272, 0.534, 0.131, 0.0, 0.0,
+                0.0, 0.0, 0.0, 1.0, 0.0,
+            ),
+            ColorFilterMode::Invert => ColorMatrix::new(
+                -1.0, 0.0, 0.0, 0.0, 1.0,
+                0.0, -1.0, 0.0, 0.0, 1.0,
+                0.0, 0.0, -1.0, 0.0, 1.0,
+                0.0, 0.0, 0.0, 1.0, 0.0,
+            ),
+        };
+        color_filters::matrix(&matrix, None)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Filter {
+    Blur,
+    ColorMatrix,
+    DropShadow,
+}
+
+impl Filter {
+    /// Builds this filter on top of `input`, the output of the previous filter in the chain.
+    pub(crate) fn image_filter(self, input: Option<ImageFilter>) -> Option<ImageFilter> {
+        match self {
+            Filter::Blur => image_filters::blur((4.0, 4.0), None, input, None),
+            Filter::ColorMatrix => {
+                let mut matrix = ColorMatrix::default();
+                matrix.set_saturation(0.0);
+                image_filters::color_filter(color_filters::matrix(&matrix, None), input, None)
+            }
+            Filter::DropShadow => {
+                image_filters::drop_shadow((6.0, 6.0), (3.0, 3.0), Color::BLACK, input, None)
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum StrokeJoin {
+    Miter,
+    Round,
+    Bevel,
+}
+
+impl StrokeJoin {
+    pub(crate) fn join(self) -> Join {
+        match self {
+            StrokeJoin::Miter => Join::Miter,
+            StrokeJoin::Round => Join::Round,
+            StrokeJoin::Bevel => Join::Bevel,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn output_stem_keeps_fractional_scale() {
+        let mut config = BenchmarkConfig::new("assets");
+        config.scale = 1.5;
+        config.save_template = Some("output-rust-scale{scale}-thread0".to_string());
+        let stem = config.output_stem(0);
+        assert_eq!(stem, Path::new("assets/output-rust-scale1.5-thread0"));
+        assert_eq!(
+            append_extension(&stem, OutputFormat::Png.extension()),
+            Path::new("assets/output-rust-scale1.5-thread0.png")
+        );
+    }
+}
@@ -0,0 +1,75 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Why a benchmark run or one of its steps failed.
+#[derive(Debug)]
+pub enum BenchError {
+    /// A required input file does not exist.
+    FileNotFound(PathBuf),
+    /// An input (image, font, SVG, path) could not be decoded; holds what was being decoded.
+    DecodeFailed(String),
+    /// The rendered output could not be encoded; holds what was being encoded.
+    EncodeFailed(String),
+    /// Reading or writing `path` failed.
+    Io { path: PathBuf, source: io::Error },
+    /// Anything else, such as an invalid combination of flags or a mismatched baseline.
+    Other(String),
+}
+
+impl BenchError {
+    /// An `Io` error for `path`.
+    pub fn io(path: &Path, source: io::Error) -> Self {
+        BenchError::Io {
+            path: path.to_path_buf(),
+            source,
+        }
+    }
+
+    /// The process exit code for this error: 2 for failed reads and writes, 3 for decode and
+    /// encode failures, and 1 for everything else, including a missing input file.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            BenchError::Io { .. } => 2,
+            BenchError::DecodeFailed(_) | BenchError::EncodeFailed(_) => 3,
+            BenchError::FileNotFound(_) | BenchError::Other(_) => 1,
+        }
+    }
+}
+
+impl fmt::Display for BenchError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BenchError::FileNotFound(path) => {
+                let name = path.file_name().unwrap_or(path.as_os_str());
+                write!(f, "required file not found: {}", name.to_string_lossy())
+            }
+            BenchError::DecodeFailed(what) => write!(f, "could not decode {}", what),
+            BenchError::EncodeFailed(what) => write!(f, "could not encode {}", what),
+            BenchError::Io { path, source } => write!(f, "{}: {}", path.display(), source),
+            BenchError::Other(message) => f.write_str(message),
+        }
+    }
+}
+
+impl Error for BenchError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            BenchError::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<String> for BenchError {
+    fn from(message: String) -> Self {
+        BenchError::Other(message)
+    }
+}
+
+impl From<&str> for BenchError {
+    fn from(message: &str) -> Self {
+        BenchError::Other(message.to_string())
+    }
+}
@@ -0,0 +1,75 @@
+use skia_safe::gpu::DirectContext;
+
+/// Creates a headless EGL pbuffer context and makes it current on this thread, where it stays for
+/// the rest of the process, then wraps it in a Skia context.
+#[cfg(all(feature = "gl", not(target_os = "macos")))]
+pub(crate) fn create_gpu_context() -> Option<DirectContext> {
+    use khronos_egl as egl;
+    use skia_safe::gpu;
+    use std::ffi::c_void;
+
+    let egl = egl::Instance::new(egl::Static);
+    let display = unsafe { egl.get_display(egl::DEFAULT_DISPLAY) }?;
+    egl.initialize(display).ok()?;
+    egl.bind_api(egl::OPENGL_API).ok()?;
+    let config_attributes = [
+        egl::SURFACE_TYPE,
+        egl::PBUFFER_BIT,
+        egl::RENDERABLE_TYPE,
+        egl::OPENGL_BIT,
+        egl::RED_SIZE,
+        8,
+        egl::GREEN_SIZE,
+        8,
+        egl::BLUE_SIZE,
+        8,
+        egl::ALPHA_SIZE,
+        8,
+        egl::STENCIL_SIZE,
+        8,
+        egl::NONE,
+    ];
+    let config = egl
+        .choose_first_config(display, &config_attributes)
+        .ok()??;
+    let surface_attributes = [egl::WIDTH, 1, egl::HEIGHT, 1, egl::NONE];
+    let surface = egl
+        .create_pbuffer_surface(display, config, &surface_attributes)
+        .ok()?;
+    let context = egl
+        .create_context(display, config, None, &[egl::NONE])
+        .ok()?;
+    egl.make_current(display, Some(surface), Some(surface), Some(context))
+        .ok()?;
+    let interface = gpu::gl::Interface::new_load_with(|name| {
+        egl.get_proc_address(name)
+            .map_or(std::ptr::null(), |address| address as *const c_void)
+    })?;
+    DirectContext::new_gl(Some(interface), None)
+}
+
+#[cfg(all(feature = "metal", target_os = "macos"))]
+pub(crate) fn create_gpu_context() -> Option<DirectContext> {
+    use foreign_types_shared::ForeignType;
+    use skia_safe::gpu;
+
+    let device = metal::Device::system_default()?;
+    let queue = device.new_command_queue();
+    let backend = unsafe {
+        gpu::mtl::BackendContext::new(
+            device.as_ptr() as gpu::mtl::Handle,
+            queue.as_ptr() as gpu::mtl::Handle,
+            std::ptr::null(),
+        )
+    };
+    DirectContext::new_metal(&backend, None)
+}
+
+#[cfg(not(any(
+    all(feature = "gl", not(target_os = "macos")),
+    all(feature = "metal", target_os = "macos")
+)))]
+pub(crate) fn create_gpu_context() -> Option<DirectContext> {
+    eprintln!("warning: built without a GPU backend, enable the `gl` or `metal` feature");
+    None
+}
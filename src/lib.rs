@@ -0,0 +1,425 @@
+mod assets;
+mod baseline;
+mod check;
+mod compare;
+mod config;
+mod error;
+mod gpu;
+mod render;
+mod report;
+mod sweep;
+mod timings;
+mod version;
+
+use crate::assets::Assets;
+use crate::gpu::create_gpu_context;
+use crate::render::{performance_test, render_pdf_page, render_surface, render_svg_out};
+use crate::report::{
+    format_memory, open_csv, print_ndjson, trace_events, write_csv_row, write_json, write_trace,
+    TimingRecord,
+};
+use crate::timings::{format_millis, format_throughput};
+use indicatif::{ProgressBar, ProgressStyle};
+use log::debug;
+use memory_stats::memory_stats;
+use skia_safe::{pdf, Surface};
+use std::io::IsTerminal;
+use std::time::{Duration, Instant};
+
+pub use crate::check::check_inputs;
+pub use crate::compare::{compare_reports, Comparison, StageDelta};
+pub use crate::config::{
+    BenchmarkConfig, Blend, ColorFilterMode, Filter, MicroPrimitive, OutputFormat, Preset,
+    Sampling, StrokeCap, StrokeJoin, SurfaceColorSpace, CANVAS_SIZE,
+};
+pub use crate::error::BenchError;
+pub use crate::sweep::{run_scale_sweep, ScaleRun, ScaleSweep};
+pub use crate::timings::{summarize, Histogram, StageStarts, StageStats, StageTimings, Summary};
+pub use crate::version::{cpu_features, format_cpu_features, version_info, VersionInfo};
+
+pub struct BenchmarkResult {
+    pub timings: Vec<StageTimings>,
+    pub summary: Summary,
+}
+
+impl BenchmarkResult {
+    fn new(timings: Vec<StageTimings>) -> Self {
+        let summary = summarize(&timings);
+        BenchmarkResult { timings, summary }
+    }
+}
+
+/// Renders a single frame of the enabled stages to a raster surface, without saving or timing it.
+pub fn render_frame(config: &BenchmarkConfig) -> Result<Surface, BenchError> {
+    let config = &config.with_default_stages();
+    let mut timings = StageTimings::default();
+    let assets = Assets::new(config)?;
+    render_surface(config, None, &assets, None, None, &mut timings)?
+        .ok_or_else(|| "could not create a raster surface".into())
+}
+
+/// Runs warmup and measured iterations for `config` and collects their timings.
+pub fn run_benchmark(config: &BenchmarkConfig) -> Result<BenchmarkResult, BenchError> {
+    let config = &config.with_default_stages();
+
+    if config.save || config.snapshot_stages || config.encode_all {
+        let output_dir = config.output_dir();
+        std::fs::create_dir_all(output_dir).map_err(|error| BenchError::io(output_dir, error))?;
+    }
+
+    if config.duration_secs.is_none() {
+        let iterations = config.frames.unwrap_or(u32::from(config.loop_count));
+        if u32::from(config.measure_from) >= iterations {
+            return Err(format!(
+                "--measure-from {} leaves none of the {} iteration(s) to measure",
+                config.measure_from, iterations
+            )
+            .into());
+        }
+    }
+
+    if config.sqlite_path.is_some() && !cfg!(feature = "rusqlite") {
+        return Err("--sqlite requires building with the rusqlite feature".into());
+    }
+
+    if config.verify_icc && config.format != OutputFormat::Png {
+        return Err("--verify-icc only works with --format png".into());
+    }
+
+    if config.msaa.is_some() && !config.gpu {
+        eprintln!("warning: --msaa only applies to GPU surfaces");
+    }
+
+    if config.threads > 1 {
+        let outputs = [
+            ("--csv", config.csv_path.is_some()),
+            ("--json", config.json_path.is_some()),
+            ("--trace", config.trace_path.is_some()),
+            ("--pdf", config.pdf_path.is_some()),
+            ("--svg-out", config.svg_out.is_some()),
+            ("--sqlite", config.sqlite_path.is_some()),
+            ("--raw", config.raw_path.is_some()),
+            ("--diff-image", config.diff_image.is_some()),
+        ];
+        let requested: Vec<&str> = outputs
+            .iter()
+            .filter(|(_, requested)| *requested)
+            .map(|(flag, _)| *flag)
+            .collect();
+        if !requested.is_empty() {
+            return Err(format!("{} only work(s) with --threads 1", requested.join(", ")).into());
+        }
+    }
+
+    if config.threads > 1 && config.gpu {
+        eprintln!("warning: --gpu is ignored when running on multiple threads");
+    }
+
+    if let (Some(core), 1) = (config.pin_core, config.threads) {
+        pin_to_core(core);
+    }
+
+    let mut gpu_context = if config.gpu && config.threads == 1 {
+        let context = create_gpu_context();
+        if context.is_none() {
+            eprintln!("warning: could not create a GPU context, falling back to raster");
+        }
+        context
+    } else {
+        None
+    };
+
+    // The timeout covers loading the inputs and the warmup too, so a slow input cannot hang a run.
+    let timeout_start = Instant::now();
+    let assets = Assets::new(config)?;
+    let mut retained = None;
+    let mut iterate = |iteration| {
+        performance_test(
+            config,
+            gpu_context.as_mut(),
+            &assets,
+            &mut retained,
+            iteration,
+        )
+    };
+
+    for _ in 0..config.warmup_count {
+        if config.timed_out(timeout_start) {
+            eprintln!("warning: --timeout reached during warmup");
+            break;
+        }
+        iterate(0)?;
+    }
+
+    if config.threads > 1 {
+        return run_threaded(config, timeout_start).map(BenchmarkResult::new);
+    }
+
+    let mut csv_file = config.csv_path.as_deref().map(open_csv).transpose()?;
+    let mut pdf_document = config.pdf_path.as_ref().map(|_| pdf::new_document(None));
+    let mut svg_data = None;
+    let mut json_records = vec![];
+    let mut trace = vec![];
+    let mut all_timings = vec![];
+
+    let progress = create_progress_bar(config);
+    let start = Instant::now();
+    while config.keep_running(all_timings.len(), start, timeout_start) {
+        let iteration = all_timings.len();
+        debug!("starting iteration {}", iteration);
+        let memory_before = memory_stats();
+        let iteration_start = start.elapsed();
+        let mut timings = iterate(iteration)?;
+        let iteration_elapsed = start.elapsed() - iteration_start;
+        let memory_after = memory_stats();
+        if let Some(document) = pdf_document.take() {
+            timings.mark_start("pdf");
+            let (document, duration) = render_pdf_page(document, config, &assets)?;
+            timings.pdf = duration;
+            pdf_document = Some(document);
+        }
+        if config.svg_out.is_some() {
+            timings.mark_start("svgout");
+            let (data, duration) = render_svg_out(config, &assets)?;
+            timings.svg_out = duration;
+            svg_data = Some(data);
+        }
+        if config.ndjson {
+            progress.suspend(|| print_ndjson(&TimingRecord::new(iteration, config, &timings)));
+        } else if !config.quiet {
+            progress.suspend(|| {
+                println!("{}", timings);
+                if config.mem {
+                    println!("{}", format_memory(memory_before, memory_after));
+                }
+            });
+        }
+        progress.inc(1);
+        if let Some(file) = csv_file.as_mut() {
+            write_csv_row(file, iteration, config.scale, &timings)?;
+        }
+        if config.json_path.is_some() {
+            json_records.push(TimingRecord::new(iteration, config, &timings));
+        }
+        if config.trace_path.is_some() {
+            trace.extend(trace_events(
+                iteration,
+                iteration_start,
+                iteration_elapsed,
+                &timings,
+            ));
+        }
+        all_timings.push(timings);
+    }
+    progress.finish_and_clear();
+    if config.wants_more(all_timings.len(), start) {
+        eprintln!(
+            "warning: --timeout reached, stopping after {} iteration(s)",
+            all_timings.len()
+        );
+    }
+    let report = !config.quiet && !config.ndjson;
+    if config.duration_secs.is_some() && report {
+        print_duration_report(all_timings.len(), start.elapsed());
+    }
+    let all_timings = measured(config, all_timings);
+    if config.frames.is_some() && report {
+        print_frame_report(&all_timings);
+    }
+    if config.dirty_rect.is_some() && report {
+        print_dirty_rect_report(&all_timings);
+    }
+
+    if let Some(json_path) = &config.json_path {
+        write_json(json_path, &json_records)?;
+    }
+    if let Some(trace_path) = &config.trace_path {
+        write_trace(trace_path, &trace)?;
+    }
+    #[cfg(feature = "rusqlite")]
+    if let Some(sqlite_path) = &config.sqlite_path {
+        let first_iteration = usize::from(config.measure_from);
+        crate::report::write_sqlite(sqlite_path, config, &all_timings, first_iteration)?;
+    }
+    if let (Some(svg_out), Some(data)) = (&config.svg_out, svg_data) {
+        std::fs::write(svg_out, data.as_bytes()).map_err(|error| BenchError::io(svg_out, error))?;
+        if report {
+            println!(
+                "svg: {} ({:.1}KiB)",
+                svg_out.display(),
+                data.len() as f64 / 1024.0
+            );
+        }
+    }
+    if let (Some(pdf_path), Some(document)) = (&config.pdf_path, pdf_document) {
+        let data = document.close();
+        std::fs::write(pdf_path, data.as_bytes())
+            .map_err(|error| BenchError::io(pdf_path, error))?;
+    }
+    Ok(BenchmarkResult::new(all_timings))
+}
+
+/// Runs `--loop` (or `--duration`) iterations on each of `--threads` threads, every one rendering to its own surface.
+fn run_threaded(
+    config: &BenchmarkConfig,
+    timeout_start: Instant,
+) -> Result<Vec<StageTimings>, BenchError> {
+    let start = Instant::now();
+    let results: Vec<Result<(Vec<StageTimings>, Duration), BenchError>> =
+        std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..config.threads)
+                .map(|index| scope.spawn(move || run_thread(config, index, timeout_start)))
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap())
+                .collect()
+        });
+    let elapsed = start.elapsed();
+
+    let mut all_timings = vec![];
+    for (index, result) in results.into_iter().enumerate() {
+        let (timings, duration) = result?;
+        if !config.quiet {
+            println!(
+                "thread {}: {}",
+                index,
+                format_throughput(timings.len(), duration)
+            );
+        }
+        all_timings.push(timings);
+    }
+    if !config.quiet {
+        let iterations = all_timings.iter().map(Vec::len).sum();
+        println!("combined: {}", format_throughput(iterations, elapsed));
+    }
+    Ok(all_timings
+        .into_iter()
+        .flat_map(|timings| measured(config, timings))
+        .collect())
+}
+
+/// Runs one of the `--threads`, saving to its own `-thread<index>` file so threads never share one.
+fn run_thread(
+    config: &BenchmarkConfig,
+    index: u16,
+    timeout_start: Instant,
+) -> Result<(Vec<StageTimings>, Duration), BenchError> {
+    let mut thread_config = config.clone();
+    thread_config.save_template = Some(format!("{}-thread{}", config.output_template(), index));
+    let config = &thread_config;
+    if let Some(core) = config.pin_core {
+        pin_to_core(core + usize::from(index));
+    }
+    let start = Instant::now();
+    let mut timings = vec![];
+    let assets = Assets::new(config)?;
+    let mut retained = None;
+    while config.keep_running(timings.len(), start, timeout_start) {
+        let iteration = timings.len();
+        timings.push(performance_test(
+            config,
+            None,
+            &assets,
+            &mut retained,
+            iteration,
+        )?);
+    }
+    if config.wants_more(timings.len(), start) {
+        eprintln!(
+            "warning: --timeout reached, thread {} stopping after {} iteration(s)",
+            index,
+            timings.len()
+        );
+    }
+    Ok((timings, start.elapsed()))
+}
+
+/// Pins the current thread to `core`, warning and carrying on unpinned if that is not possible.
+fn pin_to_core(core: usize) {
+    let pinned = core_affinity::get_core_ids()
+        .and_then(|core_ids| core_ids.into_iter().find(|core_id| core_id.id == core))
+        .map_or(false, core_affinity::set_for_current);
+    if pinned {
+        debug!("pinned to CPU core {}", core);
+    } else {
+        eprintln!(
+            "warning: could not pin to CPU core {}, running unpinned",
+            core
+        );
+    }
+}
+
+/// Drops the iterations before `--measure-from`, which only served as warmup.
+fn measured(config: &BenchmarkConfig, mut timings: Vec<StageTimings>) -> Vec<StageTimings> {
+    let skipped = usize::from(config.measure_from).min(timings.len());
+    timings.drain(..skipped);
+    timings
+}
+
+fn create_progress_bar(config: &BenchmarkConfig) -> ProgressBar {
+    if !config.progress || config.quiet || !std::io::stdout().is_terminal() {
+        return ProgressBar::hidden();
+    }
+    if config.frames.is_none() && config.duration_secs.is_some() {
+        let progress = ProgressBar::new_spinner();
+        progress.set_style(
+            ProgressStyle::with_template("{spinner} {pos} iterations in {elapsed}").unwrap(),
+        );
+        progress
+    } else {
+        let total = config.frames.unwrap_or(u32::from(config.loop_count));
+        let progress = ProgressBar::new(u64::from(total));
+        progress.set_style(
+            ProgressStyle::with_template("{bar:40} {pos}/{len} iterations, ETA {eta}").unwrap(),
+        );
+        progress
+    }
+}
+
+fn print_frame_report(timings: &[StageTimings]) {
+    if timings.is_empty() {
+        return;
+    }
+    let total: Duration = timings.iter().map(StageTimings::render_time).sum();
+    let average = total / timings.len() as u32;
+    println!(
+        "frames: {}, average frame time: {}, effective fps: {:.1}",
+        timings.len(),
+        format_millis(average),
+        1.0 / average.as_secs_f64()
+    );
+}
+
+/// Compares the full redraws of `--dirty-rect` (the first iteration) with the partial ones.
+fn print_dirty_rect_report(timings: &[StageTimings]) {
+    let average = |partial: bool| {
+        let durations: Vec<Duration> = timings
+            .iter()
+            .filter(|timings| timings.partial == partial)
+            .map(StageTimings::render_time)
+            .collect();
+        (!durations.is_empty()).then(|| durations.iter().sum::<Duration>() / durations.len() as u32)
+    };
+    match (average(false), average(true)) {
+        (Some(full), Some(partial)) => println!(
+            "dirty rect: full redraw {}, partial redraw {} on average ({:.1}x faster)",
+            format_millis(full),
+            format_millis(partial),
+            full.as_secs_f64() / partial.as_secs_f64()
+        ),
+        _ => println!("dirty rect: no full and partial redraws to compare"),
+    }
+}
+
+fn print_duration_report(iterations: usize, elapsed: Duration) {
+    if iterations == 0 {
+        println!("no iterations completed within --duration");
+        return;
+    }
+    println!(
+        "completed {}, {} per iteration on average",
+        format_throughput(iterations, elapsed),
+        format_millis(elapsed / iterations as u32)
+    );
+}
@@ -1,216 +1,222 @@
-#![allow(unused)]
-
-use clap::Parser;
-use skia_safe::canvas::SrcRectConstraint;
-use skia_safe::svg::Dom;
-use skia_safe::textlayout::{
-    FontCollection, ParagraphBuilder, ParagraphStyle, TextStyle, TypefaceFontProvider,
-};
-use skia_safe::{
-    surfaces, Canvas, Color, CubicResampler, Data, EncodedImageFormat, FilterMode, Image,
-    MipmapMode, Paint, Path as SkPath, Rect, SamplingOptions, Surface, Typeface,
+use clap::{CommandFactory, Parser, Subcommand};
+use rust_skia_performance_test::{
+    check_inputs, compare_reports, cpu_features, format_cpu_features, run_benchmark,
+    run_scale_sweep, version_info, BenchError, BenchmarkConfig, Histogram,
 };
-use std::fs::File;
-use std::io::{Read, Write};
-use std::path::{Path, PathBuf};
-
-const CANVAS_SIZE: i32 = 512;
+use std::ffi::OsString;
+use std::fmt::Display;
+use std::path::Path;
 
 #[derive(Parser)]
+#[command(
+    args_override_self = true,
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
+)]
 struct Cli {
-    #[arg(long = "dir")]
-    dir_path: std::path::PathBuf,
-    #[arg(long = "loop", default_value_t = 1)]
-    loop_count: u16,
-    #[arg(long = "path")]
-    draw_path: bool,
-    #[arg(long = "raster")]
-    draw_raster: bool,
-    #[arg(long = "text")]
-    draw_text: bool,
-    #[arg(long = "svg")]
-    draw_svg: bool,
-    #[arg(long = "save")]
-    save: bool,
-    #[arg(long = "scale", default_value_t = 1)]
-    scale: u8,
+    #[command(subcommand)]
+    command: Option<Command>,
+    /// Print the crate and skia-safe versions plus the OS and CPU architecture, then exit.
+    #[arg(long = "version-info", exclusive = true)]
+    version_info: bool,
+    /// Print the SIMD features (SSE2, AVX2, NEON, ...) detected on this CPU, then exit.
+    #[arg(long = "cpu-features", exclusive = true)]
+    cpu_features: bool,
+    /// TOML file of flag values keyed by their long names; flags given on the command line win.
+    // Read from the raw arguments by `find_config_path`, before clap parses them.
+    #[allow(dead_code)]
+    #[arg(long = "config")]
+    config_file: Option<std::path::PathBuf>,
+    #[command(flatten)]
+    config: BenchmarkConfig,
 }
 
-fn main() {
-    let mut args = Cli::parse();
+#[derive(Subcommand)]
+enum Command {
+    /// Print per-stage deltas between two reports written with `--json`.
+    Compare {
+        before: std::path::PathBuf,
+        after: std::path::PathBuf,
+        /// Percent slowdown above which a stage is flagged as a regression.
+        #[arg(long = "threshold", default_value_t = 5.0)]
+        threshold: f64,
+    },
+}
 
-    if !(args.draw_path || args.draw_raster || args.draw_text || args.draw_svg || args.save) {
-        args.draw_path = true;
-        args.draw_raster = true;
-        args.draw_text = true;
-        args.draw_svg = true;
-        args.save = true;
+fn main() {
+    let cli = match parse_args() {
+        Ok(cli) => cli,
+        Err(error) => exit_with_error(&error, error.exit_code()),
+    };
+    if cli.version_info {
+        println!("{}", version_info());
+        return;
     }
-
-    for _ in 0..args.loop_count {
-        performance_test(
-            &args.dir_path,
-            args.draw_path,
-            args.draw_raster,
-            args.draw_text,
-            args.draw_svg,
-            args.save,
-            args.scale,
-        );
+    if cli.cpu_features {
+        println!("cpu features: {}", format_cpu_features(&cpu_features()));
+        return;
     }
-}
-
-fn performance_test(
-    working_path: &PathBuf,
-    path: bool,
-    raster: bool,
-    text: bool,
-    svg: bool,
-    save: bool,
-    scale: u8,
-) {
-    if let Some(mut surface) =
-        surfaces::raster_n32_premul((CANVAS_SIZE * scale as i32, CANVAS_SIZE * scale as i32))
+    if let Some(Command::Compare {
+        before,
+        after,
+        threshold,
+    }) = &cli.command
     {
-        let mut paint = Paint::default();
-        paint.set_anti_alias(true);
-        let canvas = surface.canvas();
-        canvas.clear(Color::WHITE);
-        canvas.scale((scale as f32, scale as f32));
-        if path {
-            let path_path = check_file_exists(working_path.join("path.txt"));
-            draw_path(canvas, &mut paint, &path_path);
-        }
-        if raster {
-            let raster_path = check_file_exists(working_path.join("mars.jpg"));
-            draw_raster(canvas, &mut paint, &raster_path);
-        }
-        if text {
-            let font_path = check_file_exists(working_path.join("Adigiana_Ultra.ttf"));
-            draw_text(canvas, &font_path);
-        }
-        if svg {
-            let svg_path = check_file_exists(working_path.join("pinocchio.svg"));
-            draw_svg(canvas, &svg_path);
-        }
-        if save {
-            let output_path = working_path.join("output-rust.png");
-            save_to_png(&mut surface, &output_path);
+        match compare_reports(before, after, *threshold) {
+            Ok(comparison) => println!("{}", comparison),
+            Err(error) => exit_with_error(&error, error.exit_code()),
         }
+        return;
     }
-}
-
-fn check_file_exists(path: PathBuf) -> PathBuf {
-    if !path.exists() {
-        panic!("File doesn't exist: {:?}", path);
+    let config = cli.config;
+    let default_filter = if config.verbose { "debug" } else { "warn" };
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or(default_filter))
+        .format_timestamp_millis()
+        .init();
+
+    let outcome = if config.check {
+        check_inputs(&config)
+    } else if config.scale_sweep.is_some() {
+        run_scale_sweep(&config).map(|sweep| {
+            if !config.quiet && !config.ndjson {
+                println!("{}", sweep);
+            }
+        })
+    } else {
+        run_benchmark(&config).map(|result| {
+            if !config.quiet && !config.ndjson {
+                println!("{}", result.summary);
+                if config.histogram {
+                    let bins = usize::from(config.histogram_bins);
+                    println!("{}", Histogram::new(&result.timings, bins));
+                }
+            }
+        })
+    };
+    if let Err(error) = outcome {
+        exit_with_error(&error, error.exit_code());
     }
-    path
 }
 
-fn draw_path(canvas: &mut Canvas, paint: &mut Paint, path_path: &PathBuf) {
-    paint.set_color(Color::BLACK);
-    canvas.save();
-    canvas.translate((12.0, 12.0));
-    canvas.scale((0.45, 0.45));
-    if let Ok(path_def) = std::fs::read_to_string(path_path) {
-        if let Some(path) = SkPath::from_svg(path_def) {
-            canvas.draw_path(&path, paint);
-        }
-    }
-    canvas.restore();
+fn exit_with_error(message: &dyn Display, code: i32) -> ! {
+    eprintln!("error: {}", message);
+    std::process::exit(code);
 }
 
-fn draw_raster(canvas: &mut Canvas, paint: &mut Paint, raster_path: &PathBuf) {
-    canvas.save();
-    canvas.translate((250.0, 0.0));
-    canvas.scale((0.05, 0.05));
-    if let Ok(bitmap_data) = data_from_file_path(raster_path) {
-        if let Some(bitmap) = Image::from_encoded(bitmap_data) {
-            let rect = Rect::from_wh(bitmap.width() as f32, bitmap.height() as f32);
-            canvas.draw_image_rect_with_sampling_options(
-                bitmap,
-                Some((&rect, SrcRectConstraint::Fast)),
-                &rect,
-                SamplingOptions::new(FilterMode::Linear, MipmapMode::Linear),
-                &paint,
-            );
-        }
+/// Parses the command line, placing the values from `--config` in front. File keys that the
+/// command line sets, or that conflict with a flag given there, are dropped so explicit flags win.
+fn parse_args() -> Result<Cli, BenchError> {
+    let mut args: Vec<OsString> = std::env::args_os().collect();
+    if let Some(config_path) = find_config_path(&args) {
+        let explicit = explicit_flags(&args);
+        let file_args = config_file_args(Path::new(&config_path), &explicit)?;
+        args.splice(1..1, file_args.into_iter().map(OsString::from));
     }
-    canvas.restore();
+    Ok(Cli::parse_from(args))
 }
 
-fn draw_text(canvas: &mut Canvas, font_path: &PathBuf) {
-    let mut typeface_provider = TypefaceFontProvider::new();
-    if let Ok(data) = data_from_file_path(font_path) {
-        if let Some(font) = Typeface::from_data(data, None) {
-            typeface_provider.register_typeface(font, Some("Adigiana"));
+fn find_config_path(args: &[OsString]) -> Option<OsString> {
+    let mut args = args.iter().skip(1);
+    while let Some(arg) = args.next() {
+        let arg = arg.to_string_lossy();
+        if arg == "--config" {
+            return args.next().cloned();
         }
-    }
-    let mut font_collection = FontCollection::new();
-    font_collection.set_asset_font_manager(Some(typeface_provider.into()));
-
-    let mut style = ParagraphStyle::new();
-    let mut text_style = TextStyle::new();
-    text_style.set_color(Color::from_rgb(0, 0, 0));
-    text_style.set_font_size(15.0);
-    text_style.set_font_families(&["Adigiana"]);
-    style.set_text_style(&text_style);
-    let mut paragraph_builder = ParagraphBuilder::new(&style, font_collection);
-    paragraph_builder.add_text("Lorem ipsum dolor sit amet, consectetur adipiscing elit, ");
-    text_style.set_color(Color::from_rgb(255, 0, 0));
-    paragraph_builder.push_style(&text_style);
-    paragraph_builder
-        .add_text("sed do eiusmod tempor incididunt ut labore et dolore magna aliqua. ");
-    text_style.set_color(Color::from_rgb(0, 255, 0));
-    paragraph_builder.push_style(&text_style);
-    paragraph_builder
-        .add_text("Ut enim ad minim veniam, quis nostrud exercitation ullamco laboris nisi ut ");
-    text_style.set_color(Color::from_rgb(0, 0, 255));
-    paragraph_builder.push_style(&text_style);
-    paragraph_builder
-        .add_text("aliquip ex ea commodo consequat. Duis aute irure dolor in reprehenderit in ");
-    text_style.set_color(Color::from_rgb(255, 255, 0));
-    paragraph_builder.push_style(&text_style);
-    paragraph_builder
-        .add_text("voluptate velit esse cillum dolore eu fugiat nulla pariatur. Excepteur sint ");
-    text_style.set_color(Color::from_rgb(0, 255, 255));
-    paragraph_builder.push_style(&text_style);
-    paragraph_builder.add_text("occaecat cupidatat non proident, sunt in culpa qui officia deserunt mollit anim id est laborum.\n");
-
-    let mut paragraph = paragraph_builder.build();
-    paragraph.layout(225.0);
-
-    paragraph.paint(canvas, (25.0, 275.0));
-}
-
-fn draw_svg(canvas: &mut Canvas, svg_path: &PathBuf) {
-    canvas.save();
-    canvas.translate((350.0, 275.0));
-    canvas.scale((0.22, 0.22));
-    if let Ok(svg_data) = bytes_from_file_path(svg_path) {
-        if let Ok(svg) = Dom::from_bytes(&svg_data) {
-            svg.render(canvas);
+        if let Some(path) = arg.strip_prefix("--config=") {
+            return Some(path.into());
         }
     }
-    canvas.restore();
+    None
 }
 
-fn data_from_file_path(file_path: &Path) -> std::io::Result<Data> {
-    bytes_from_file_path(file_path).map(|bytes| Data::new_copy(&bytes.as_slice()))
+/// The long names of the flags given on the command line, without their values.
+fn explicit_flags(args: &[OsString]) -> Vec<String> {
+    args.iter()
+        .skip(1)
+        .filter_map(|arg| {
+            let arg = arg.to_string_lossy();
+            let flag = arg.strip_prefix("--")?;
+            Some(flag.split('=').next().unwrap_or(flag).to_string())
+        })
+        .collect()
 }
 
-fn bytes_from_file_path(file_path: &Path) -> std::io::Result<Vec<u8>> {
-    let mut file = File::open(file_path).unwrap();
-    let mut bytes = vec![];
-    file.read_to_end(&mut bytes).map(|_| bytes)
+/// Whether the config file `key` is overridden by an explicit flag: the same flag, or one that
+/// conflicts with it in either direction.
+fn overridden_by_cli(command: &clap::Command, key: &str, explicit: &[String]) -> bool {
+    let long_arg = |long: &str| {
+        command
+            .get_arguments()
+            .find(|arg| arg.get_long() == Some(long))
+    };
+    let file_arg = match long_arg(key) {
+        Some(arg) => arg,
+        None => return false,
+    };
+    let conflicts = |arg: &clap::Arg, other: &clap::Arg| {
+        command
+            .get_arg_conflicts_with(arg)
+            .iter()
+            .any(|conflict| conflict.get_id() == other.get_id())
+    };
+    explicit
+        .iter()
+        .filter_map(|flag| long_arg(flag))
+        .any(|cli_arg| {
+            cli_arg.get_id() == file_arg.get_id()
+                || conflicts(file_arg, cli_arg)
+                || conflicts(cli_arg, file_arg)
+        })
 }
 
-fn save_to_png(surface: &mut Surface, output_path: &PathBuf) {
-    let image = surface.image_snapshot();
-    let mut context = surface.direct_context();
-    if let Some(data) = image.encode(context.as_mut(), EncodedImageFormat::PNG, None) {
-        let mut file = File::create(output_path).unwrap();
-        let bytes = data.as_bytes();
-        file.write_all(bytes).unwrap();
+fn config_file_args(config_path: &Path, explicit: &[String]) -> Result<Vec<String>, BenchError> {
+    let text =
+        std::fs::read_to_string(config_path).map_err(|error| BenchError::io(config_path, error))?;
+    let table: toml::Table = text.parse().map_err(|error| {
+        BenchError::DecodeFailed(format!("config {}: {}", config_path.display(), error))
+    })?;
+    let command = Cli::command();
+    let known: Vec<&str> = command
+        .get_arguments()
+        .filter_map(|arg| arg.get_long())
+        .filter(|long| *long != "config")
+        .collect();
+    let unknown: Vec<&str> = table
+        .keys()
+        .map(String::as_str)
+        .filter(|key| !known.contains(key))
+        .collect();
+    if !unknown.is_empty() {
+        return Err(format!(
+            "unknown key(s) in {}: {}",
+            config_path.display(),
+            unknown.join(", ")
+        )
+        .into());
+    }
+
+    let mut args = vec![];
+    for (key, value) in &table {
+        if overridden_by_cli(&command, key, explicit) {
+            continue;
+        }
+        let flag = format!("--{}", key);
+        match value {
+            toml::Value::Boolean(true) => args.push(flag),
+            toml::Value::Boolean(false) => {}
+            // Joined with `=` so that negative values are not mistaken for flags.
+            toml::Value::String(value) => args.push(format!("{}={}", flag, value)),
+            toml::Value::Integer(value) => args.push(format!("{}={}", flag, value)),
+            toml::Value::Float(value) => args.push(format!("{}={}", flag, value)),
+            _ => {
+                return Err(format!(
+                    "unsupported value for '{}' in {}",
+                    key,
+                    config_path.display()
+                )
+                .into())
+            }
+        }
     }
+    Ok(args)
 }
@@ -0,0 +1,1827 @@
+use crate::assets::Assets;
+use crate::baseline::{compare_with_baseline, diff_with_reference};
+use crate::config::{
+    append_extension, BenchmarkConfig, MicroPrimitive, OutputFormat, Preset, Sampling, CANVAS_SIZE,
+    ENCODED_FORMATS,
+};
+use crate::error::BenchError;
+use crate::timings::{measure, try_measure, StageTimings};
+use log::debug;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use sha2::{Digest, Sha256};
+use skia_safe::canvas::SrcRectConstraint;
+use skia_safe::gpu::{self, DirectContext};
+use skia_safe::graphics;
+use skia_safe::image::CachingHint;
+use skia_safe::paint::Style as PaintStyle;
+use skia_safe::svg::{self, Dom};
+use skia_safe::textlayout::{
+    FontCollection, Paragraph, ParagraphBuilder, ParagraphStyle, TextDirection, TextShadow,
+    TextStyle, TypefaceFontProvider,
+};
+use skia_safe::vertices::VertexMode;
+use skia_safe::{
+    image_filters, surfaces, AlphaType, BlendMode, Canvas, Color, ColorSpace, ColorType,
+    ContourMeasureIter, Data, Document, EncodedImageFormat, FilterMode, Font, Image, ImageInfo,
+    Matrix, MipmapMode, Paint, Path as SkPath, PathEffect, PictureRecorder, Point, RSXform, Rect,
+    SamplingOptions, Shader, Surface, TextBlob, TileMode, Typeface, Vertices,
+};
+use skia_safe::{path_utils, png_encoder};
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+/// Renders one iteration. With `--no-clear`, `--reuse-surface` or `--dirty-rect` the surface is kept
+/// in `retained` for the next iteration, which draws over it, clears it or redraws the dirty rect.
+pub(crate) fn performance_test(
+    config: &BenchmarkConfig,
+    mut gpu_context: Option<&mut DirectContext>,
+    assets: &Assets,
+    retained: &mut Option<Surface>,
+    iteration: usize,
+) -> Result<StageTimings, BenchError> {
+    let mut timings = StageTimings {
+        origin: Some(Instant::now()),
+        ..StageTimings::default()
+    };
+    if let (true, Some(context)) = (config.gpu_readback, gpu_context.as_deref_mut()) {
+        let raster_path = check_file_exists(config.dir_path.join(&config.raster_file))?;
+        let image = assets.decoded_image(&raster_path)?;
+        timings.mark_start("upload");
+        timings.upload = measure(|| upload_image(context, &image, &raster_path));
+    }
+    let mut previous = retained.take();
+    if config.dirty_rect.is_some() && iteration == 0 {
+        previous = None;
+    }
+    let frame = config.frame_progress(iteration);
+    if let Some(mut surface) =
+        render_surface(config, gpu_context, assets, frame, previous, &mut timings)?
+    {
+        if config.gpu_readback && surface.direct_context().is_some() {
+            let mut pixels = None;
+            timings.mark_start("readback");
+            timings.readback = measure(|| pixels = surface_pixels(&mut surface));
+            if pixels.is_none() {
+                eprintln!("warning: could not read back the GPU surface");
+            }
+        }
+        if config.hash {
+            match surface_pixels(&mut surface) {
+                Some(pixels) => {
+                    print_diagnostic(config, &format!("sha256: {:x}", Sha256::digest(&pixels)))
+                }
+                None => eprintln!("warning: could not read surface pixels for hashing"),
+            }
+        }
+        if let Some(baseline_path) = &config.baseline_path {
+            compare_with_baseline(&mut surface, baseline_path, config.tolerance)?;
+        }
+        if let Some(reference_path) = &config.diff_reference {
+            let summary =
+                diff_with_reference(&mut surface, reference_path, config.diff_image.as_deref())?;
+            print_diagnostic(config, &summary);
+        }
+        if let Some(raw_path) = &config.raw_path {
+            save_raw(&mut surface, raw_path)?;
+        }
+        if config.save {
+            let output_stem = config.output_stem(iteration);
+            let mut saved = None;
+            let mut save = || {
+                saved = Some(save_image(
+                    &mut surface,
+                    &output_stem,
+                    config.format,
+                    config.quality,
+                    config.png_level,
+                    config.save_if_changed,
+                ))
+            };
+            if config.measure_save {
+                timings.mark_start("save");
+                timings.save = measure(save);
+            } else {
+                save();
+            }
+            let saved_path = saved.transpose()?.map(|saved| {
+                timings.save_skipped = saved.skipped;
+                saved.path
+            });
+            if let Some(saved_path) = &saved_path {
+                timings.saved_bytes = std::fs::metadata(saved_path)
+                    .map(|metadata| metadata.len())
+                    .unwrap_or(0);
+            }
+            if let (true, Some(saved_path)) = (config.verify_icc, &saved_path) {
+                verify_icc(saved_path, &config.colorspace.color_space())?;
+            }
+            if let (true, Some(saved_path)) = (config.transparent, saved_path) {
+                check_saved_alpha(&saved_path);
+            }
+        }
+        if config.encode_all {
+            timings.encodes = encode_all(
+                &mut surface,
+                &config.output_stem(iteration),
+                config.quality,
+                config.png_level,
+            )?;
+        }
+        if config.no_clear || config.reuse_surface || config.dirty_rect.is_some() {
+            *retained = Some(surface);
+        }
+    }
+    Ok(timings)
+}
+
+/// Prints a per-iteration diagnostic line to stdout, or to stderr under `--ndjson` so that stdout
+/// stays one JSON record per line.
+fn print_diagnostic(config: &BenchmarkConfig, line: &str) {
+    if config.ndjson {
+        eprintln!("{}", line);
+    } else {
+        println!("{}", line);
+    }
+}
+
+/// Uploads the decoded raster image into a GPU texture and waits for the upload to be submitted.
+fn upload_image(context: &mut DirectContext, image: &Image, raster_path: &Path) {
+    if image
+        .new_texture_image(context, gpu::Mipmapped::No)
+        .is_none()
+    {
+        eprintln!(
+            "warning: could not upload {} to the GPU",
+            raster_path.display()
+        );
+    }
+    context.flush_and_submit();
+}
+
+/// Draws the enabled stages onto `previous` without clearing it, or onto a fresh surface.
+///
+/// Returns `None` if no surface could be created.
+pub(crate) fn render_surface(
+    config: &BenchmarkConfig,
+    gpu_context: Option<&mut DirectContext>,
+    assets: &Assets,
+    frame: Option<f32>,
+    previous: Option<Surface>,
+    timings: &mut StageTimings,
+) -> Result<Option<Surface>, BenchError> {
+    let scale = config.scale;
+    let size = config.surface_size();
+    let layout = Layout::new(config.width, config.height);
+    let color_space = config.colorspace.color_space();
+    let is_fresh = previous.is_none();
+    timings.mark_start("surface");
+    let start = Instant::now();
+    let alpha_type = if config.unpremul {
+        AlphaType::Unpremul
+    } else {
+        AlphaType::Premul
+    };
+    let msaa = match (config.msaa, gpu_context.as_ref()) {
+        (Some(requested), Some(context)) => {
+            let max = context.max_surface_sample_count_for_color_type(ColorType::N32);
+            Some(supported_sample_count(requested, max))
+        }
+        _ => None,
+    };
+    timings.msaa_samples = msaa.unwrap_or(0);
+    let created =
+        previous.or_else(|| create_surface(size, color_space, alpha_type, msaa, gpu_context));
+    if is_fresh {
+        timings.surface = start.elapsed();
+    }
+    let mut surface = match created {
+        Some(surface) => surface,
+        None => return Ok(None),
+    };
+    if config.measure_surface_create {
+        return Ok(Some(surface));
+    }
+    let partial = !is_fresh && config.dirty_rect.is_some();
+    timings.partial = partial;
+    if let (true, Some(dirty_rect)) = (partial, config.dirty_rect) {
+        let canvas = surface.canvas();
+        canvas.save();
+        canvas.clip_rect(
+            Rect::from_ltrb(
+                dirty_rect.left * scale,
+                dirty_rect.top * scale,
+                dirty_rect.right * scale,
+                dirty_rect.bottom * scale,
+            ),
+            None,
+            false,
+        );
+    }
+    if is_fresh || config.reuse_surface || partial {
+        timings.mark_start("clear");
+        timings.clear = measure(|| {
+            let canvas = surface.canvas();
+            canvas.clear(config.clear_color());
+            if config.checkerboard {
+                draw_checkerboard(canvas, config.checker_size);
+            }
+        });
+    }
+    let mut paint = shared_paint(config);
+    if config.tile.is_some() {
+        render_tiles(
+            &mut surface,
+            &mut paint,
+            config,
+            assets,
+            frame,
+            layout,
+            timings,
+        )?;
+        if config.grid {
+            let canvas = surface.canvas();
+            canvas.save();
+            canvas.scale((scale, scale));
+            draw_grid(canvas, config.grid_spacing, (config.width, config.height));
+            canvas.restore();
+        }
+        return Ok(Some(surface));
+    }
+    let canvas = surface.canvas();
+    canvas.save();
+    canvas.scale((scale, scale));
+    if config.picture {
+        let mut recorder = PictureRecorder::new();
+        let bounds = Rect::from_iwh(config.width, config.height);
+        timings.mark_start("record");
+        let start = Instant::now();
+        let recording_canvas = recorder.begin_recording(bounds, None);
+        draw_stages(
+            recording_canvas,
+            &mut paint,
+            config,
+            assets,
+            frame,
+            layout,
+            timings,
+        )?;
+        let picture = recorder.finish_recording_as_picture(None);
+        timings.record = start.elapsed();
+        if let Some(picture) = picture {
+            timings.mark_start("playback");
+            timings.playback = measure(|| canvas.draw_picture(&picture, None, None));
+        }
+    } else {
+        draw_stages(canvas, &mut paint, config, assets, frame, layout, timings)?;
+    }
+    if config.grid {
+        draw_grid(canvas, config.grid_spacing, (config.width, config.height));
+    }
+    canvas.restore();
+    if partial {
+        canvas.restore();
+    }
+    Ok(Some(surface))
+}
+
+/// Draws hairlines every `spacing` units across the canvas, as a visual aid for checking placement.
+fn draw_grid(canvas: &mut Canvas, spacing: f32, (width, height): (i32, i32)) {
+    let (width, height) = (width as f32, height as f32);
+    let mut paint = Paint::default();
+    paint.set_color(Color::from_argb(64, 0, 128, 255));
+    paint.set_style(PaintStyle::Stroke);
+    let mut x = 0.0;
+    while x <= width {
+        canvas.draw_line((x, 0.0), (x, height), &paint);
+        x += spacing;
+    }
+    let mut y = 0.0;
+    while y <= height {
+        canvas.draw_line((0.0, y), (width, y), &paint);
+        y += spacing;
+    }
+}
+
+/// Fills the canvas with a repeating image shader of gray `size`-pixel squares on every other cell.
+fn draw_checkerboard(canvas: &mut Canvas, size: i32) {
+    let mut tile = match surfaces::raster_n32_premul((size * 2, size * 2)) {
+        Some(tile) => tile,
+        None => return,
+    };
+    let mut square_paint = Paint::default();
+    square_paint.set_color(Color::from_rgb(204, 204, 204));
+    let side = size as f32;
+    tile.canvas()
+        .draw_rect(Rect::from_xywh(0.0, 0.0, side, side), &square_paint)
+        .draw_rect(Rect::from_xywh(side, side, side, side), &square_paint);
+    let mut paint = Paint::default();
+    paint.set_shader(tile.image_snapshot().to_shader(
+        (TileMode::Repeat, TileMode::Repeat),
+        SamplingOptions::default(),
+        None,
+    ));
+    canvas.draw_paint(&paint);
+}
+
+/// The paint shared by the path, shape and raster stages.
+fn shared_paint(config: &BenchmarkConfig) -> Paint {
+    let mut paint = Paint::default();
+    paint.set_anti_alias(!config.no_aa);
+    if let Some(blend) = config.blend {
+        paint.set_blend_mode(blend.mode());
+    }
+    paint
+}
+
+/// Renders the scene as `--tile` horizontal strips on separate surfaces, then stitches them into `surface`.
+fn render_tiles(
+    surface: &mut Surface,
+    paint: &mut Paint,
+    config: &BenchmarkConfig,
+    assets: &Assets,
+    frame: Option<f32>,
+    layout: Layout,
+    timings: &mut StageTimings,
+) -> Result<(), BenchError> {
+    let tile_count = config.tile.unwrap_or(1);
+    let scale = config.scale;
+    let tile_height = (surface.height() + tile_count as i32 - 1) / tile_count as i32;
+    let tile_info = surface
+        .image_info()
+        .with_dimensions((surface.width(), tile_height));
+    let mut tiles = vec![];
+    timings.mark_start("tiles");
+    let start = Instant::now();
+    for index in 0..tile_count {
+        let top = index as i32 * tile_height;
+        let mut tile = surface
+            .new_surface(&tile_info)
+            .ok_or_else(|| "could not create a tile surface".to_string())?;
+        let canvas = tile.canvas();
+        canvas.clear(config.clear_color());
+        canvas.translate((0.0, -top as f32));
+        canvas.scale((scale, scale));
+        let mut tile_timings = StageTimings::default();
+        draw_stages(
+            canvas,
+            paint,
+            config,
+            assets,
+            frame,
+            layout,
+            &mut tile_timings,
+        )?;
+        tiles.push((top, tile.image_snapshot()));
+    }
+    timings.tiles = start.elapsed();
+    timings.tile_count = tile_count;
+    let canvas = surface.canvas();
+    timings.mark_start("stitch");
+    timings.stitch = measure(|| {
+        for (top, image) in &tiles {
+            canvas.draw_image(image, (0.0, *top as f32), None);
+        }
+    });
+    Ok(())
+}
+
+fn draw_stages(
+    canvas: &mut Canvas,
+    paint: &mut Paint,
+    config: &BenchmarkConfig,
+    assets: &Assets,
+    frame: Option<f32>,
+    layout: Layout,
+    timings: &mut StageTimings,
+) -> Result<(), BenchError> {
+    canvas.save();
+    if let Some(clip) = config.clip {
+        canvas.clip_rect(clip, None, !config.no_aa);
+    }
+    if config.clip_path {
+        let path_path = check_file_exists(config.dir_path.join("path.txt"))?;
+        if let Some(clip_path) = outline_path(&path_path, layout) {
+            canvas.clip_path(&clip_path, None, !config.no_aa);
+        }
+    }
+    let center = (config.width as f32 / 2.0, config.height as f32 / 2.0);
+    let step = 360.0 / config.transform_count as f32;
+    let start = Instant::now();
+    let mut result = Ok(());
+    for pass in 0..config.transform_count {
+        canvas.save();
+        canvas.rotate(config.rotate + pass as f32 * step, Some(center.into()));
+        result = draw_enabled_stages(canvas, paint, config, assets, frame, layout, timings);
+        canvas.restore();
+        if result.is_err() {
+            break;
+        }
+    }
+    timings.transform_count = config.transform_count;
+    timings.transformed = start.elapsed();
+    canvas.restore();
+    result
+}
+
+/// Draws each enabled stage, adding to `timings` so repeated `--transform-count` passes sum up;
+/// `frame` is the animation progress in `--frames` mode.
+fn draw_enabled_stages(
+    canvas: &mut Canvas,
+    paint: &mut Paint,
+    config: &BenchmarkConfig,
+    assets: &Assets,
+    frame: Option<f32>,
+    layout: Layout,
+    timings: &mut StageTimings,
+) -> Result<(), BenchError> {
+    let working_path = &config.dir_path;
+    let text_opacity = frame.unwrap_or(1.0);
+    let paragraph_options = ParagraphOptions {
+        rtl: config.rtl,
+        shadow: config.text_shadow(),
+        letter_spacing: config.letter_spacing,
+        word_spacing: config.word_spacing,
+    };
+    let svg_rotation = frame.map_or(0.0, |progress| progress * 360.0);
+    if config.draw_path {
+        let mut path_paint = paint.clone();
+        if let Some(stroke_width) = config.stroke_width {
+            path_paint.set_style(PaintStyle::Stroke);
+            path_paint.set_stroke_width(stroke_width);
+            path_paint.set_stroke_cap(config.stroke_cap.cap());
+            path_paint.set_stroke_join(config.stroke_join.join());
+        }
+        if let Some((on, off)) = config.dash {
+            path_paint.set_path_effect(PathEffect::dash(&[on, off], 0.0));
+            timings.dashed = true;
+        }
+        if let Some(path_file) = &config.path_file {
+            let path_file = check_file_exists(working_path.join(path_file))?;
+            let mut counts = (0, 0);
+            timings.mark_start("path");
+            timings.path += measure(|| {
+                counts = draw_path_grid(
+                    canvas,
+                    &mut path_paint,
+                    &path_file,
+                    (config.width, config.height),
+                    config.gradient,
+                )
+            });
+            (timings.paths_parsed, timings.paths_failed) = counts;
+        } else {
+            let path_path = check_file_exists(working_path.join("path.txt"))?;
+            timings.mark_start("path");
+            timings.path +=
+                measure(|| draw_path(canvas, &mut path_paint, &path_path, layout, config.gradient));
+        }
+    }
+    if config.draw_path {
+        snapshot_stage(canvas, config, "path");
+    }
+    if config.path_outline {
+        let path_path = check_file_exists(working_path.join("path.txt"))?;
+        let stroke_width = config.stroke_width.unwrap_or(4.0);
+        timings.mark_start("outline");
+        timings.outline +=
+            measure(|| draw_stroke_outline(canvas, paint, &path_path, layout, stroke_width));
+        snapshot_stage(canvas, config, "outline");
+    }
+    if let Some(count) = config.random_shapes {
+        timings.mark_start("random");
+        timings.random += measure(|| {
+            draw_random_shapes(
+                canvas,
+                paint,
+                config.seed,
+                count,
+                config.width,
+                config.height,
+            )
+        });
+    }
+    if config.random_shapes.is_some() {
+        snapshot_stage(canvas, config, "random");
+    }
+    if config.draw_shapes {
+        timings.mark_start("shapes");
+        timings.shapes += measure(|| {
+            draw_shapes(
+                canvas,
+                paint,
+                (config.round_rect_count, config.oval_count),
+                (config.width, config.height),
+            )
+        });
+        snapshot_stage(canvas, config, "shapes");
+    }
+    if let Some(count) = config.mesh {
+        let vertices = mesh_vertices(config.seed, count, (config.width, config.height));
+        timings.mark_start("mesh");
+        timings.mesh += measure(|| {
+            canvas.draw_vertices(&vertices, BlendMode::Dst, paint);
+        });
+        snapshot_stage(canvas, config, "mesh");
+    }
+    if let Some(primitive) = config.micro {
+        timings.mark_start("micro");
+        timings.micro += measure(|| {
+            draw_micro(
+                canvas,
+                paint,
+                primitive,
+                config.micro_count,
+                (config.width, config.height),
+            )
+        });
+        timings.micro_draws += config.micro_count;
+        snapshot_stage(canvas, config, "micro");
+    }
+    if config.fill_rate {
+        timings.mark_start("fill");
+        timings.fill += measure(|| {
+            draw_fill_circles(
+                canvas,
+                paint,
+                config.fill_count,
+                (config.width, config.height),
+            )
+        });
+        let (width, height) = config.surface_size();
+        timings.fill_pixels += u64::from(config.fill_count) * width as u64 * height as u64;
+        snapshot_stage(canvas, config, "fill");
+    }
+    if config.draw_raster {
+        let raster_path = check_file_exists(working_path.join(&config.raster_file))?;
+        let mut raster_paint = paint.clone();
+        if let Some(color_filter) = config.color_filter {
+            raster_paint.set_color_filter(color_filter.color_filter());
+            timings.color_filter = Some(color_filter);
+        }
+        if let Some(grid) = config.raster_grid {
+            let mut image_draws = 0;
+            timings.mark_start("raster");
+            timings.raster += try_measure(|| {
+                let bitmap = assets.image(&raster_path)?;
+                image_draws = draw_raster_grid(
+                    canvas,
+                    &mut raster_paint,
+                    &bitmap,
+                    grid,
+                    (config.width, config.height),
+                    config.sampling,
+                );
+                Ok(())
+            })?;
+            timings.image_draws += image_draws;
+        } else {
+            timings.mark_start("raster");
+            timings.raster += try_measure(|| {
+                let bitmap = assets.image(&raster_path)?;
+                draw_raster(canvas, &mut raster_paint, &bitmap, layout, config.sampling);
+                Ok(())
+            })?;
+            timings.image_draws += 1;
+        }
+    }
+    if config.draw_raster {
+        snapshot_stage(canvas, config, "raster");
+    }
+    if config.decode_only {
+        let raster_path = check_file_exists(working_path.join(&config.raster_file))?;
+        timings.mark_start("decode");
+        timings.decode += try_measure(|| decode_raster(&raster_path).map(drop))?;
+    }
+    if config.gen_mipmaps {
+        let raster_path = check_file_exists(working_path.join(&config.raster_file))?;
+        let decoded = decode_raster(&raster_path)?;
+        let mut mipmapped = None;
+        timings.mark_start("mipbuild");
+        timings.mip_build += measure(|| mipmapped = decoded.with_default_mipmaps());
+        if let Some(mipmapped) = &mipmapped {
+            timings.mark_start("mipdraw");
+            timings.mip_draw += measure(|| draw_downscaled(canvas, paint, mipmapped, layout));
+        }
+        snapshot_stage(canvas, config, "mipmaps");
+    }
+    if let Some(sigma) = config.blur_sigma {
+        let raster_path = check_file_exists(working_path.join(&config.raster_file))?;
+        let mut blur_paint = paint.clone();
+        blur_paint.set_image_filter(image_filters::blur((sigma, sigma), None, None, None));
+        let bitmap = assets.decoded_image(&raster_path)?;
+        timings.mark_start("blur");
+        timings.blur += measure(|| {
+            draw_raster(canvas, &mut blur_paint, &bitmap, layout, config.sampling);
+        });
+    }
+    if config.blur_sigma.is_some() {
+        snapshot_stage(canvas, config, "blur");
+    }
+    if !config.filter_chain.is_empty() {
+        let raster_path = check_file_exists(working_path.join(&config.raster_file))?;
+        let mut filter_paint = paint.clone();
+        let chain = config
+            .filter_chain
+            .iter()
+            .fold(None, |input, filter| filter.image_filter(input));
+        filter_paint.set_image_filter(chain);
+        let bitmap = assets.decoded_image(&raster_path)?;
+        timings.mark_start("filters");
+        timings.filters += measure(|| {
+            draw_raster(canvas, &mut filter_paint, &bitmap, layout, config.sampling);
+        });
+        snapshot_stage(canvas, config, "filters");
+    }
+    if config.draw_text {
+        let font_path = check_file_exists(working_path.join(&config.font_file))?;
+        timings.mark_start("text");
+        timings.text += try_measure(|| {
+            let font_collection = assets.font_collection(&font_path)?;
+            draw_text(
+                canvas,
+                &font_collection,
+                layout,
+                config.text_count,
+                config.text_width,
+                &paragraph_options,
+                text_opacity,
+            );
+            Ok(())
+        })?;
+        timings.text_count += config.text_count;
+        if config.glyph_cache_stats {
+            let mut font_collection = assets.font_collection(&font_path)?;
+            (timings.glyph_cold, timings.glyph_warm) = measure_glyph_cache(
+                &mut font_collection,
+                (config.width, config.height),
+                config.text_width,
+                &paragraph_options,
+            );
+        }
+        if let Some(count) = config.text_reflow {
+            timings.mark_start("reflow");
+            timings.reflow += try_measure(|| {
+                let font_collection = assets.font_collection(&font_path)?;
+                reflow_text(
+                    &font_collection,
+                    config.text_width,
+                    count,
+                    &paragraph_options,
+                );
+                Ok(())
+            })?;
+        }
+        if let Some(fallback_font) = &config.fallback_font {
+            let fallback_path = check_file_exists(working_path.join(fallback_font))?;
+            let primary_only = assets.font_collection(&font_path)?;
+            let with_fallback = assets.fallback_collection(&font_path, &fallback_path)?;
+            timings.mark_start("nofallback");
+            timings.no_fallback += measure(|| {
+                draw_fallback_text(
+                    canvas,
+                    primary_only,
+                    &["Adigiana"],
+                    layout.position(25.0, 490.0),
+                )
+            });
+            timings.mark_start("fallback");
+            timings.fallback += measure(|| {
+                draw_fallback_text(
+                    canvas,
+                    with_fallback,
+                    &["Adigiana", "Fallback"],
+                    layout.position(25.0, 470.0),
+                )
+            });
+        }
+    }
+    if config.draw_text {
+        snapshot_stage(canvas, config, "text");
+    }
+    if let Some(fonts_dir) = &config.fonts_dir {
+        let font_paths = find_fonts(&working_path.join(fonts_dir))?;
+        let font_data: Vec<Data> = font_paths
+            .iter()
+            .filter_map(|path| data_from_file_path(path).ok())
+            .collect();
+        let mut registered = 0;
+        timings.mark_start("fonts");
+        timings.fonts += measure(|| registered = register_fonts(&font_data));
+        timings.fonts_registered += registered;
+    }
+    if config.simple_text {
+        let font_path = check_file_exists(working_path.join(&config.font_file))?;
+        let typeface = assets.typeface(&font_path)?;
+        timings.mark_start("simpletext");
+        timings.simple_text += measure(|| draw_simple_text(canvas, paint, typeface, layout));
+        snapshot_stage(canvas, config, "simpletext");
+    }
+    if config.text_on_path {
+        let path_path = check_file_exists(working_path.join("path.txt"))?;
+        let font_path = check_file_exists(working_path.join(&config.font_file))?;
+        let typeface = assets.typeface(&font_path)?;
+        timings.mark_start("textpath");
+        timings.text_path +=
+            try_measure(|| draw_text_on_path(canvas, paint, &path_path, typeface, layout))?;
+        snapshot_stage(canvas, config, "textpath");
+    }
+    if config.draw_svg {
+        if let Some(pattern) = &config.svg_glob {
+            let svg_paths = find_svgs(working_path, pattern)?;
+            let mut svg_count = 0;
+            timings.mark_start("svg");
+            timings.svg += try_measure(|| {
+                let svgs = assets.svg_grid(&svg_paths)?;
+                svg_count = draw_svg_grid(canvas, svgs, config.width, config.height);
+                Ok(())
+            })?;
+            timings.svg_count += svg_count;
+        } else {
+            let svg_path = if config.svg_from_stream() {
+                None
+            } else {
+                Some(check_file_exists(working_path.join(&config.svg_file))?)
+            };
+            timings.mark_start("svg");
+            timings.svg += try_measure(|| {
+                let svg = assets.svg(svg_path.as_deref())?;
+                draw_svg(canvas, &svg, layout, svg_rotation);
+                Ok(())
+            })?;
+        }
+    }
+    if config.draw_svg {
+        snapshot_stage(canvas, config, "svg");
+    }
+    if let Some(preset) = config.preset {
+        let raster_path = check_file_exists(working_path.join(&config.raster_file))?;
+        let font_path = check_file_exists(working_path.join(&config.font_file))?;
+        let typeface = assets.typeface(&font_path)?;
+        let image = assets.decoded_image(&raster_path)?;
+        timings.mark_start("preset");
+        timings.preset += measure(|| match preset {
+            Preset::Ui => draw_ui_preset(
+                canvas,
+                paint,
+                &image,
+                typeface,
+                (config.width, config.height),
+            ),
+        });
+        snapshot_stage(canvas, config, "preset");
+    }
+    Ok(())
+}
+
+/// Saves what has been drawn so far as `after-<stage>.png` when `--snapshot-stages` is set.
+fn snapshot_stage(canvas: &mut Canvas, config: &BenchmarkConfig, stage: &str) {
+    if !config.snapshot_stages {
+        return;
+    }
+    let image_info = ImageInfo::new_n32_premul(canvas.base_layer_size(), None);
+    let row_bytes = image_info.min_row_bytes();
+    let mut pixels = vec![0; row_bytes * image_info.height() as usize];
+    if !canvas.read_pixels(&image_info, &mut pixels, row_bytes, (0, 0)) {
+        eprintln!(
+            "warning: could not read canvas pixels for the {} snapshot",
+            stage
+        );
+        return;
+    }
+    let image = Image::from_raster_data(&image_info, Data::new_copy(&pixels), row_bytes);
+    if let Some(data) = image.and_then(|image| image.encode(None, EncodedImageFormat::PNG, None)) {
+        let snapshot_path = config.output_dir().join(format!("after-{}.png", stage));
+        if let Err(error) = std::fs::write(&snapshot_path, data.as_bytes()) {
+            eprintln!(
+                "warning: could not write {}: {}",
+                snapshot_path.display(),
+                error
+            );
+        }
+    }
+}
+
+pub(crate) fn render_pdf_page(
+    document: Document,
+    config: &BenchmarkConfig,
+    assets: &Assets,
+) -> Result<(Document, Duration), BenchError> {
+    let start = Instant::now();
+    let mut page = document.begin_page((config.width as f32, config.height as f32), None);
+    let mut paint = shared_paint(config);
+    let layout = Layout::new(config.width, config.height);
+    let mut page_timings = StageTimings::default();
+    draw_stages(
+        page.canvas(),
+        &mut paint,
+        config,
+        assets,
+        None,
+        layout,
+        &mut page_timings,
+    )?;
+    let document = page.end_page();
+    Ok((document, start.elapsed()))
+}
+
+/// Draws the enabled stages onto an SVG canvas and returns the serialized document.
+pub(crate) fn render_svg_out(
+    config: &BenchmarkConfig,
+    assets: &Assets,
+) -> Result<(Data, Duration), BenchError> {
+    let start = Instant::now();
+    let bounds = Rect::from_iwh(config.width, config.height);
+    let mut canvas = svg::Canvas::new(bounds, None);
+    let mut paint = shared_paint(config);
+    let layout = Layout::new(config.width, config.height);
+    let mut svg_timings = StageTimings::default();
+    draw_stages(
+        &mut canvas,
+        &mut paint,
+        config,
+        assets,
+        None,
+        layout,
+        &mut svg_timings,
+    )?;
+    let data = canvas.end();
+    Ok((data, start.elapsed()))
+}
+
+fn create_surface(
+    size: (i32, i32),
+    color_space: ColorSpace,
+    alpha_type: AlphaType,
+    msaa: Option<u32>,
+    gpu_context: Option<&mut DirectContext>,
+) -> Option<Surface> {
+    debug!("creating {}x{} surface...", size.0, size.1);
+    let mut image_info = ImageInfo::new_n32(size, alpha_type, Some(color_space));
+    if alpha_type == AlphaType::Unpremul && !unpremul_supported(&image_info) {
+        image_info = image_info.with_alpha_type(AlphaType::Premul);
+    }
+    if let Some(context) = gpu_context {
+        let surface = gpu::surfaces::render_target(
+            context,
+            gpu::Budgeted::Yes,
+            &image_info,
+            msaa.map(|samples| samples as usize),
+            gpu::SurfaceOrigin::BottomLeft,
+            None,
+            false,
+        );
+        if surface.is_some() {
+            return surface;
+        }
+        eprintln!("warning: could not create a GPU surface, falling back to raster");
+    }
+    surfaces::raster(&image_info, None, None)
+}
+
+/// Picks the power-of-two sample count up to the context's `max` that is closest to `requested`.
+fn supported_sample_count(requested: u32, max: usize) -> u32 {
+    let max = max.max(1) as u32;
+    (0..5)
+        .map(|shift| 1 << shift)
+        .filter(|samples| *samples <= max)
+        .min_by_key(|samples: &u32| samples.abs_diff(requested))
+        .unwrap_or(1)
+}
+
+/// Probes once whether Skia accepts an unpremultiplied surface, warning on the first fallback.
+fn unpremul_supported(image_info: &ImageInfo) -> bool {
+    static SUPPORTED: OnceLock<bool> = OnceLock::new();
+    *SUPPORTED.get_or_init(|| {
+        let supported = surfaces::raster(image_info, None, None).is_some();
+        if !supported {
+            eprintln!(
+                "warning: unpremultiplied surfaces are not supported, falling back to premul"
+            );
+        }
+        supported
+    })
+}
+
+pub(crate) fn surface_pixels(surface: &mut Surface) -> Option<Vec<u8>> {
+    let image_info = surface.image_info();
+    let row_bytes = image_info.min_row_bytes();
+    let mut pixels = vec![0; row_bytes * image_info.height() as usize];
+    if surface.read_pixels(&image_info, &mut pixels, row_bytes, (0, 0)) {
+        Some(pixels)
+    } else {
+        None
+    }
+}
+
+fn save_raw(surface: &mut Surface, raw_path: &Path) -> Result<(), BenchError> {
+    let image_info = ImageInfo::new(
+        surface.image_info().dimensions(),
+        ColorType::RGBA8888,
+        AlphaType::Premul,
+        None,
+    );
+    let row_bytes = image_info.min_row_bytes();
+    let mut pixels = vec![0; row_bytes * image_info.height() as usize];
+    if !surface.read_pixels(&image_info, &mut pixels, row_bytes, (0, 0)) {
+        return Err("could not read surface pixels for --raw".into());
+    }
+    let meta = format!(
+        "width={}\nheight={}\nrow_bytes={}\nformat=rgba8888_premul\n",
+        image_info.width(),
+        image_info.height(),
+        row_bytes
+    );
+    let mut meta_path = raw_path.as_os_str().to_owned();
+    meta_path.push(".meta");
+    std::fs::write(raw_path, &pixels)
+        .and_then(|_| std::fs::write(&meta_path, meta))
+        .map_err(|error| BenchError::io(raw_path, error))
+}
+
+pub(crate) fn check_file_exists(path: PathBuf) -> Result<PathBuf, BenchError> {
+    if !path.exists() {
+        return Err(BenchError::FileNotFound(path));
+    }
+    Ok(path)
+}
+
+/// Maps positions designed for a `CANVAS_SIZE` square onto the actual canvas dimensions.
+#[derive(Clone, Copy)]
+struct Layout {
+    x_factor: f32,
+    y_factor: f32,
+}
+
+impl Layout {
+    fn new(width: i32, height: i32) -> Self {
+        Layout {
+            x_factor: width as f32 / CANVAS_SIZE as f32,
+            y_factor: height as f32 / CANVAS_SIZE as f32,
+        }
+    }
+
+    fn position(&self, x: f32, y: f32) -> (f32, f32) {
+        (x * self.x_factor, y * self.y_factor)
+    }
+}
+
+fn draw_path(
+    canvas: &mut Canvas,
+    paint: &mut Paint,
+    path_path: &PathBuf,
+    layout: Layout,
+    gradient: bool,
+) {
+    paint.set_color(Color::BLACK);
+    canvas.save();
+    canvas.translate(layout.position(12.0, 12.0));
+    canvas.scale((0.45, 0.45));
+    debug!("parsing path {}...", path_path.display());
+    if let Ok(path_def) = std::fs::read_to_string(path_path) {
+        if let Some(path) = SkPath::from_svg(path_def) {
+            if gradient {
+                let mut gradient_paint = paint.clone();
+                gradient_paint.set_shader(path_gradient(path.bounds()));
+                canvas.draw_path(&path, &gradient_paint);
+            } else {
+                canvas.draw_path(&path, paint);
+            }
+        }
+    }
+    canvas.restore();
+}
+
+/// Draws every SVG path in `path_file` scaled into its own grid cell, returning how many parsed and failed.
+fn draw_path_grid(
+    canvas: &mut Canvas,
+    paint: &mut Paint,
+    path_file: &PathBuf,
+    (width, height): (i32, i32),
+    gradient: bool,
+) -> (usize, usize) {
+    paint.set_color(Color::BLACK);
+    debug!("parsing paths {}...", path_file.display());
+    let path_defs = std::fs::read_to_string(path_file).unwrap_or_default();
+    let lines: Vec<&str> = path_defs
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .collect();
+    let paths: Vec<SkPath> = lines.iter().filter_map(SkPath::from_svg).collect();
+    let failed = lines.len() - paths.len();
+    if paths.is_empty() {
+        return (0, failed);
+    }
+    let columns = (paths.len() as f32).sqrt().ceil() as usize;
+    let rows = (paths.len() + columns - 1) / columns;
+    let cell_width = width as f32 / columns as f32;
+    let cell_height = height as f32 / rows as f32;
+    for (index, path) in paths.iter().enumerate() {
+        let bounds = path.bounds();
+        let fit = (cell_width / bounds.width()).min(cell_height / bounds.height());
+        canvas.save();
+        canvas.translate((
+            (index % columns) as f32 * cell_width,
+            (index / columns) as f32 * cell_height,
+        ));
+        if fit.is_finite() {
+            canvas.scale((fit, fit));
+        }
+        canvas.translate((-bounds.left, -bounds.top));
+        if gradient {
+            let mut gradient_paint = paint.clone();
+            gradient_paint.set_shader(path_gradient(bounds));
+            canvas.draw_path(path, &gradient_paint);
+        } else {
+            canvas.draw_path(path, paint);
+        }
+        canvas.restore();
+    }
+    (paths.len(), failed)
+}
+
+/// Loads the path from `path_path` with the same placement `draw_path` uses.
+fn outline_path(path_path: &PathBuf, layout: Layout) -> Option<SkPath> {
+    let path_def = std::fs::read_to_string(path_path).ok()?;
+    let path = SkPath::from_svg(path_def)?;
+    let (x, y) = layout.position(12.0, 12.0);
+    let matrix = Matrix::concat(&Matrix::translate((x, y)), &Matrix::scale((0.45, 0.45)));
+    Some(path.with_transform(&matrix))
+}
+
+/// Turns the stroke of the path into a fill path of its own and fills that, instead of stroking.
+fn draw_stroke_outline(
+    canvas: &mut Canvas,
+    paint: &Paint,
+    path_path: &PathBuf,
+    layout: Layout,
+    stroke_width: f32,
+) {
+    let path = match outline_path(path_path, layout) {
+        Some(path) => path,
+        None => return,
+    };
+    let mut stroke_paint = paint.clone();
+    stroke_paint.set_style(PaintStyle::Stroke);
+    stroke_paint.set_stroke_width(stroke_width);
+    let mut outline = SkPath::new();
+    if !path_utils::fill_path_with_paint(&path, &stroke_paint, &mut outline, None, None) {
+        return;
+    }
+    let mut fill_paint = paint.clone();
+    fill_paint.set_color(Color::from_rgb(128, 0, 128));
+    canvas.draw_path(&outline, &fill_paint);
+}
+
+fn path_gradient(bounds: &Rect) -> Option<Shader> {
+    Shader::linear_gradient(
+        ((bounds.left, bounds.top), (bounds.right, bounds.bottom)),
+        [Color::BLUE, Color::MAGENTA, Color::RED].as_ref(),
+        None,
+        TileMode::Clamp,
+        None,
+        None,
+    )
+}
+
+/// Draws `count` randomly placed rectangles and circles; the same seed always yields the same scene.
+fn draw_random_shapes(
+    canvas: &mut Canvas,
+    paint: &Paint,
+    seed: u64,
+    count: u32,
+    width: i32,
+    height: i32,
+) {
+    let paint = &mut paint.clone();
+    let mut rng = StdRng::seed_from_u64(seed);
+    for _ in 0..count {
+        paint.set_color(Color::from_argb(
+            rng.gen_range(64..=255),
+            rng.gen(),
+            rng.gen(),
+            rng.gen(),
+        ));
+        let x = rng.gen_range(0.0..width as f32);
+        let y = rng.gen_range(0.0..height as f32);
+        let size = rng.gen_range(4.0..64.0);
+        if rng.gen_bool(0.5) {
+            canvas.draw_rect(Rect::from_xywh(x, y, size, size), paint);
+        } else {
+            canvas.draw_circle((x, y), size / 2.0, paint);
+        }
+    }
+}
+
+/// Draws `count` anti-aliased circles, each big enough to cover the whole canvas, alternating colors
+/// so every draw overwrites the previous one.
+fn draw_fill_circles(canvas: &mut Canvas, paint: &Paint, count: u32, (width, height): (i32, i32)) {
+    let paint = &mut paint.clone();
+    let center = (width as f32 / 2.0, height as f32 / 2.0);
+    let radius = center.0.hypot(center.1);
+    paint.set_anti_alias(true);
+    for index in 0..count {
+        paint.set_color(if index % 2 == 0 {
+            Color::from_rgb(0, 128, 255)
+        } else {
+            Color::from_rgb(255, 128, 0)
+        });
+        canvas.draw_circle(center, radius, paint);
+    }
+}
+
+/// Builds `count` randomly placed triangles with a random color per vertex; the same seed always
+/// yields the same mesh.
+fn mesh_vertices(seed: u64, count: u32, (width, height): (i32, i32)) -> Vertices {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut positions = Vec::with_capacity(count as usize * 3);
+    let mut colors = Vec::with_capacity(count as usize * 3);
+    for _ in 0..count {
+        let x = rng.gen_range(0.0..width as f32);
+        let y = rng.gen_range(0.0..height as f32);
+        for _ in 0..3 {
+            positions.push(Point::new(
+                x + rng.gen_range(-32.0..32.0),
+                y + rng.gen_range(-32.0..32.0),
+            ));
+            colors.push(Color::from_argb(
+                rng.gen_range(64..=255),
+                rng.gen(),
+                rng.gen(),
+                rng.gen(),
+            ));
+        }
+    }
+    Vertices::new_copy(VertexMode::Triangles, &positions, &[], &colors, None)
+}
+
+/// Draws the rounded rectangles, then the ovals, one per 32px cell, wrapping around the canvas.
+fn draw_shapes(
+    canvas: &mut Canvas,
+    paint: &Paint,
+    (round_rects, ovals): (u32, u32),
+    (width, height): (i32, i32),
+) {
+    let paint = &mut paint.clone();
+    let columns = (width / 32).max(1) as u32;
+    let rows = (height / 32).max(1) as u32;
+    for index in 0..round_rects + ovals {
+        let x = (index % columns) as f32 * 32.0;
+        let y = (index / columns % rows) as f32 * 32.0;
+        let bounds = Rect::from_xywh(x + 4.0, y + 8.0, 24.0, 16.0);
+        if index < round_rects {
+            paint.set_color(Color::from_argb(192, 0, 128, 255));
+            canvas.draw_round_rect(bounds, 6.0, 6.0, paint);
+        } else {
+            paint.set_color(Color::from_argb(192, 255, 128, 0));
+            canvas.draw_oval(bounds, paint);
+        }
+    }
+}
+
+/// A mock app screen: background, toolbar, two columns of shadowed cards with labels and one thumbnail.
+fn draw_ui_preset(
+    canvas: &mut Canvas,
+    paint: &Paint,
+    image: &Image,
+    typeface: Typeface,
+    (width, height): (i32, i32),
+) {
+    let paint = &mut paint.clone();
+    let (width, height) = (width as f32, height as f32);
+    let font = Font::from_typeface(typeface, 14.0);
+    let mut text_paint = Paint::default();
+    text_paint.set_anti_alias(true);
+
+    paint.set_color(Color::from_rgb(240, 242, 245));
+    canvas.draw_rect(Rect::from_wh(width, height), paint);
+    paint.set_color(Color::from_rgb(33, 150, 243));
+    canvas.draw_rect(Rect::from_wh(width, 48.0), paint);
+    text_paint.set_color(Color::WHITE);
+    canvas.draw_str("Dashboard", (16.0, 30.0), &font, &text_paint);
+
+    let card_width = (width - 48.0) / 2.0;
+    let card_height = 96.0;
+    for index in 0..8 {
+        let x = 16.0 + (index % 2) as f32 * (card_width + 16.0);
+        let y = 64.0 + (index / 2) as f32 * (card_height + 16.0);
+        let card = Rect::from_xywh(x, y, card_width, card_height);
+        paint.set_color(Color::from_argb(40, 0, 0, 0));
+        canvas.draw_round_rect(card.with_offset((0.0, 2.0)), 8.0, 8.0, paint);
+        paint.set_color(Color::WHITE);
+        canvas.draw_round_rect(card, 8.0, 8.0, paint);
+        text_paint.set_color(Color::from_rgb(33, 33, 33));
+        canvas.draw_str(
+            format!("Card {}", index + 1),
+            (x + 12.0, y + 24.0),
+            &font,
+            &text_paint,
+        );
+        text_paint.set_color(Color::from_rgb(117, 117, 117));
+        canvas.draw_str(
+            "Lorem ipsum dolor sit amet",
+            (x + 12.0, y + 46.0),
+            &font,
+            &text_paint,
+        );
+    }
+    let thumbnail = Rect::from_xywh(16.0 + card_width - 76.0, 76.0, 64.0, 64.0);
+    canvas.draw_image_rect(image, None, thumbnail, paint);
+}
+
+/// Draws `count` copies of one primitive, cycling through positions across the canvas.
+fn draw_micro(
+    canvas: &mut Canvas,
+    paint: &Paint,
+    primitive: MicroPrimitive,
+    count: u32,
+    (width, height): (i32, i32),
+) {
+    let paint = &mut paint.clone();
+    paint.set_color(Color::from_rgb(0, 0, 128));
+    let font = Font::default();
+    let columns = (width / 16).max(1) as u32;
+    let rows = (height / 16).max(1) as u32;
+    for index in 0..count {
+        let x = (index % columns) as f32 * 16.0;
+        let y = (index / columns % rows) as f32 * 16.0;
+        match primitive {
+            MicroPrimitive::Circle => {
+                canvas.draw_circle((x + 8.0, y + 8.0), 6.0, paint);
+            }
+            MicroPrimitive::Rect => {
+                canvas.draw_rect(Rect::from_xywh(x + 2.0, y + 2.0, 12.0, 12.0), paint);
+            }
+            MicroPrimitive::Line => {
+                canvas.draw_line((x, y), (x + 16.0, y + 16.0), paint);
+            }
+            MicroPrimitive::Text => {
+                canvas.draw_str("Ag", (x, y + 12.0), &font, paint);
+            }
+        }
+    }
+}
+
+fn draw_raster(
+    canvas: &mut Canvas,
+    paint: &mut Paint,
+    bitmap: &Image,
+    layout: Layout,
+    sampling: Sampling,
+) {
+    canvas.save();
+    canvas.translate(layout.position(250.0, 0.0));
+    canvas.scale((0.05, 0.05));
+    let rect = Rect::from_wh(bitmap.width() as f32, bitmap.height() as f32);
+    canvas.draw_image_rect_with_sampling_options(
+        bitmap,
+        Some((&rect, SrcRectConstraint::Fast)),
+        &rect,
+        sampling.options(),
+        &paint,
+    );
+    canvas.restore();
+}
+
+/// Draws the image at 1/8, 1/16 and 1/32 of its size with linear mipmap sampling.
+fn draw_downscaled(canvas: &mut Canvas, paint: &Paint, image: &Image, layout: Layout) {
+    let sampling = SamplingOptions::new(FilterMode::Linear, MipmapMode::Linear);
+    let (mut x, y) = layout.position(250.0, 150.0);
+    for divisor in [8.0, 16.0, 32.0] {
+        let width = image.width() as f32 / divisor;
+        let height = image.height() as f32 / divisor;
+        let rect = Rect::from_xywh(x, y, width, height);
+        canvas.draw_image_rect_with_sampling_options(image, None, rect, sampling, paint);
+        x += width + 4.0;
+    }
+}
+
+/// Decodes the image and forces it into raster memory, so lazy decoding doesn't defer the work.
+fn decode_raster(raster_path: &Path) -> Result<Image, BenchError> {
+    debug!("decoding image {}...", raster_path.display());
+    let bitmap_data =
+        data_from_file_path(raster_path).map_err(|error| BenchError::io(raster_path, error))?;
+    Image::from_encoded(bitmap_data)
+        .and_then(|image| image.make_raster_image(CachingHint::Disallow))
+        .ok_or_else(|| BenchError::DecodeFailed(raster_path.display().to_string()))
+}
+
+fn draw_raster_grid(
+    canvas: &mut Canvas,
+    paint: &mut Paint,
+    bitmap: &Image,
+    (rows, cols): (u32, u32),
+    (width, height): (i32, i32),
+    sampling: Sampling,
+) -> usize {
+    let src = Rect::from_wh(bitmap.width() as f32, bitmap.height() as f32);
+    let cell_width = width as f32 / cols as f32;
+    let cell_height = height as f32 / rows as f32;
+    let mut draws = 0;
+    for row in 0..rows {
+        for col in 0..cols {
+            let dst = Rect::from_xywh(
+                col as f32 * cell_width,
+                row as f32 * cell_height,
+                cell_width,
+                cell_height,
+            );
+            canvas.draw_image_rect_with_sampling_options(
+                bitmap,
+                Some((&src, SrcRectConstraint::Fast)),
+                dst,
+                sampling.options(),
+                &paint,
+            );
+            draws += 1;
+        }
+    }
+    draws
+}
+
+fn draw_text(
+    canvas: &mut Canvas,
+    font_collection: &FontCollection,
+    layout: Layout,
+    text_count: u32,
+    text_width: f32,
+    options: &ParagraphOptions,
+    opacity: f32,
+) {
+    let (x, y) = layout.position(25.0, 275.0);
+    let faded = opacity < 1.0;
+    if faded {
+        canvas.save_layer_alpha(None, (opacity.clamp(0.0, 1.0) * 255.0) as u32);
+    }
+    for index in 0..text_count {
+        let mut paragraph = build_paragraph(font_collection, options);
+        debug!("laying out paragraph {}...", index);
+        paragraph.layout(text_width);
+        let offset = (index % 16) as f32 * 4.0;
+        paragraph.paint(canvas, (x + offset, y + offset));
+    }
+    if faded {
+        canvas.restore();
+    }
+}
+
+/// Paints one paragraph right after purging the glyph and paragraph caches, then again warm.
+fn measure_glyph_cache(
+    font_collection: &mut FontCollection,
+    size: (i32, i32),
+    text_width: f32,
+    options: &ParagraphOptions,
+) -> (Duration, Duration) {
+    let mut scratch = match surfaces::raster_n32_premul(size) {
+        Some(scratch) => scratch,
+        None => return (Duration::ZERO, Duration::ZERO),
+    };
+    let layout = Layout::new(size.0, size.1);
+    graphics::purge_font_cache();
+    font_collection.clear_caches();
+    let mut paint = || {
+        measure(|| {
+            draw_text(
+                scratch.canvas(),
+                font_collection,
+                layout,
+                1,
+                text_width,
+                options,
+                1.0,
+            )
+        })
+    };
+    let cold = paint();
+    let warm = paint();
+    (cold, warm)
+}
+
+/// Lays out one paragraph `count` times at widths spread around `text_width`.
+fn reflow_text(
+    font_collection: &FontCollection,
+    text_width: f32,
+    count: u32,
+    options: &ParagraphOptions,
+) {
+    let mut paragraph = build_paragraph(font_collection, options);
+    for index in 0..count {
+        let fraction = if count > 1 {
+            index as f32 / (count - 1) as f32
+        } else {
+            0.5
+        };
+        paragraph.layout(text_width * (0.5 + fraction));
+    }
+    debug!("reflowed paragraph {} times", count);
+}
+
+/// Lays out a line of CJK and emoji text that the primary font has to fall back for, using the
+/// given font families in order.
+fn draw_fallback_text(
+    canvas: &mut Canvas,
+    font_collection: FontCollection,
+    families: &[&str],
+    position: (f32, f32),
+) {
+    let mut style = ParagraphStyle::new();
+    let mut text_style = TextStyle::new();
+    text_style.set_color(Color::from_rgb(0, 0, 0));
+    text_style.set_font_size(15.0);
+    text_style.set_font_families(families);
+    style.set_text_style(&text_style);
+    let mut paragraph_builder = ParagraphBuilder::new(&style, font_collection);
+    paragraph_builder.add_text("Lorem ipsum 你好世界 こんにちは 안녕하세요 🌍🚀✨\n");
+    let mut paragraph = paragraph_builder.build();
+    debug!("laying out fallback paragraph...");
+    paragraph.layout(225.0);
+    paragraph.paint(canvas, position);
+}
+
+/// Draws the sample text as plain glyph runs with a `Font`, one `draw_str` per line.
+fn draw_simple_text(canvas: &mut Canvas, paint: &Paint, typeface: Typeface, layout: Layout) {
+    let font = Font::from_typeface(typeface, 15.0);
+    let mut text_paint = paint.clone();
+    text_paint.set_color(Color::BLACK);
+    let lines = [
+        "Lorem ipsum dolor sit amet, consectetur adipiscing elit,",
+        "sed do eiusmod tempor incididunt ut labore et dolore",
+        "magna aliqua. Ut enim ad minim veniam, quis nostrud",
+        "exercitation ullamco laboris nisi ut aliquip ex ea commodo.",
+    ];
+    let (x, y) = layout.position(25.0, 400.0);
+    for (index, line) in lines.iter().enumerate() {
+        canvas.draw_str(line, (x, y + index as f32 * 18.0), &font, &text_paint);
+    }
+}
+
+/// Repeats the sample text along every contour of the path, placing and rotating each glyph on its own.
+fn draw_text_on_path(
+    canvas: &mut Canvas,
+    paint: &Paint,
+    path_path: &Path,
+    typeface: Typeface,
+    layout: Layout,
+) -> Result<(), BenchError> {
+    debug!("parsing path {}...", path_path.display());
+    let path_def =
+        std::fs::read_to_string(path_path).map_err(|error| BenchError::io(path_path, error))?;
+    let path = SkPath::from_svg(path_def)
+        .ok_or_else(|| BenchError::DecodeFailed(path_path.display().to_string()))?;
+    let font = Font::from_typeface(typeface, 30.0);
+    let glyphs =
+        font.str_to_glyphs_vec("Lorem ipsum dolor sit amet, consectetur adipiscing elit. ");
+    let mut widths = vec![0.0; glyphs.len()];
+    font.get_widths(&glyphs, &mut widths);
+    if widths.iter().sum::<f32>() <= 0.0 {
+        return Ok(());
+    }
+    let mut text_paint = paint.clone();
+    text_paint.set_color(Color::BLACK);
+    canvas.save();
+    canvas.translate(layout.position(12.0, 12.0));
+    canvas.scale((0.45, 0.45));
+    for contour in ContourMeasureIter::new(&path, false, None) {
+        let mut placed = vec![];
+        let mut xforms = vec![];
+        let mut distance = 0.0;
+        for (glyph, width) in glyphs.iter().zip(&widths).cycle() {
+            let middle = distance + width / 2.0;
+            if middle > contour.length() {
+                break;
+            }
+            let (point, tangent) = match contour.pos_tan(middle) {
+                Some(position) => position,
+                None => break,
+            };
+            let origin = (
+                point.x - tangent.x * width / 2.0,
+                point.y - tangent.y * width / 2.0,
+            );
+            xforms.push(RSXform::new(tangent.x, tangent.y, origin));
+            placed.push(*glyph);
+            distance += width;
+        }
+        if let Some(blob) = TextBlob::from_rsxform(&placed[..], &xforms, &font) {
+            canvas.draw_text_blob(&blob, (0.0, 0.0), &text_paint);
+        }
+    }
+    canvas.restore();
+    Ok(())
+}
+
+pub(crate) fn load_typeface(font_path: &Path) -> Result<Typeface, BenchError> {
+    debug!("loading font {}...", font_path.display());
+    let data = data_from_file_path(font_path).map_err(|error| BenchError::io(font_path, error))?;
+    Typeface::from_data(data, None)
+        .ok_or_else(|| BenchError::DecodeFailed(font_path.display().to_string()))
+}
+
+pub(crate) fn load_font_collection(
+    font_path: &Path,
+    fallback_path: Option<&PathBuf>,
+) -> Result<FontCollection, BenchError> {
+    let mut typeface_provider = TypefaceFontProvider::new();
+    let fonts = [
+        (Some(font_path), "Adigiana"),
+        (fallback_path.map(PathBuf::as_path), "Fallback"),
+    ];
+    for (path, family) in fonts {
+        if let Some(path) = path {
+            typeface_provider.register_typeface(load_typeface(path)?, Some(family));
+        }
+    }
+    let mut font_collection = FontCollection::new();
+    font_collection.set_asset_font_manager(Some(typeface_provider.into()));
+    Ok(font_collection)
+}
+
+/// Per-run variations of the sample paragraph.
+struct ParagraphOptions {
+    rtl: bool,
+    shadow: Option<TextShadow>,
+    letter_spacing: Option<f32>,
+    word_spacing: Option<f32>,
+}
+
+fn build_paragraph(font_collection: &FontCollection, options: &ParagraphOptions) -> Paragraph {
+    let rtl = options.rtl;
+    let mut style = ParagraphStyle::new();
+    if rtl {
+        style.set_text_direction(TextDirection::RTL);
+    }
+    let mut text_style = TextStyle::new();
+    if let Some(shadow) = options.shadow {
+        text_style.add_shadow(shadow);
+    }
+    text_style.set_color(Color::from_rgb(0, 0, 0));
+    text_style.set_font_size(15.0);
+    text_style.set_font_families(&["Adigiana"]);
+    if let Some(letter_spacing) = options.letter_spacing {
+        text_style.set_letter_spacing(letter_spacing);
+    }
+    if let Some(word_spacing) = options.word_spacing {
+        text_style.set_word_spacing(word_spacing);
+    }
+    style.set_text_style(&text_style);
+    let mut paragraph_builder = ParagraphBuilder::new(&style, font_collection.clone());
+    paragraph_builder.add_text("Lorem ipsum dolor sit amet, consectetur adipiscing elit, ");
+    text_style.set_color(Color::from_rgb(255, 0, 0));
+    paragraph_builder.push_style(&text_style);
+    paragraph_builder
+        .add_text("sed do eiusmod tempor incididunt ut labore et dolore magna aliqua. ");
+    text_style.set_color(Color::from_rgb(0, 255, 0));
+    paragraph_builder.push_style(&text_style);
+    paragraph_builder
+        .add_text("Ut enim ad minim veniam, quis nostrud exercitation ullamco laboris nisi ut ");
+    text_style.set_color(Color::from_rgb(0, 0, 255));
+    paragraph_builder.push_style(&text_style);
+    paragraph_builder
+        .add_text("aliquip ex ea commodo consequat. Duis aute irure dolor in reprehenderit in ");
+    text_style.set_color(Color::from_rgb(255, 255, 0));
+    paragraph_builder.push_style(&text_style);
+    paragraph_builder
+        .add_text("voluptate velit esse cillum dolore eu fugiat nulla pariatur. Excepteur sint ");
+    text_style.set_color(Color::from_rgb(0, 255, 255));
+    paragraph_builder.push_style(&text_style);
+    paragraph_builder.add_text("occaecat cupidatat non proident, sunt in culpa qui officia deserunt mollit anim id est laborum.\n");
+    if rtl {
+        text_style.set_color(Color::from_rgb(255, 0, 255));
+        paragraph_builder.push_style(&text_style);
+        paragraph_builder.add_text("مرحبا بالعالم 123، שלום עולם (ABC) نص تجريبي ");
+        paragraph_builder.add_text("וטקסט לדוגמה.\n");
+    }
+
+    paragraph_builder.build()
+}
+
+fn draw_svg(canvas: &mut Canvas, svg: &Dom, layout: Layout, rotation: f32) {
+    canvas.save();
+    canvas.translate(layout.position(350.0, 275.0));
+    canvas.scale((0.22, 0.22));
+    if rotation != 0.0 {
+        let size = svg.container_size();
+        canvas.rotate(rotation, Some((size.width / 2.0, size.height / 2.0).into()));
+    }
+    svg.render(canvas);
+    canvas.restore();
+}
+
+/// Lists the `.ttf` and `.otf` files directly inside `fonts_dir`.
+pub(crate) fn find_fonts(fonts_dir: &Path) -> Result<Vec<PathBuf>, BenchError> {
+    let entries = std::fs::read_dir(fonts_dir).map_err(|error| BenchError::io(fonts_dir, error))?;
+    let mut font_paths: Vec<PathBuf> = entries
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.extension()
+                .and_then(|extension| extension.to_str())
+                .map_or(false, |extension| {
+                    extension.eq_ignore_ascii_case("ttf") || extension.eq_ignore_ascii_case("otf")
+                })
+        })
+        .collect();
+    if font_paths.is_empty() {
+        return Err(format!("no .ttf or .otf files in {}", fonts_dir.display()).into());
+    }
+    font_paths.sort();
+    Ok(font_paths)
+}
+
+/// Registers each already read font with a fresh provider and returns how many could be loaded.
+fn register_fonts(font_data: &[Data]) -> usize {
+    let mut typeface_provider = TypefaceFontProvider::new();
+    let mut registered = 0;
+    for data in font_data {
+        if let Some(typeface) = Typeface::from_data(data.clone(), None) {
+            typeface_provider.register_typeface(typeface, None::<&str>);
+            registered += 1;
+        }
+    }
+    registered
+}
+
+pub(crate) fn find_svgs(working_path: &Path, pattern: &str) -> Result<Vec<PathBuf>, BenchError> {
+    let pattern = working_path.join(pattern);
+    let paths = glob::glob(&pattern.to_string_lossy())
+        .map_err(|error| format!("invalid SVG glob pattern: {}", error))?;
+    let svg_paths: Vec<PathBuf> = paths.filter_map(Result::ok).collect();
+    if svg_paths.is_empty() {
+        return Err(format!("no SVG files match: {}", pattern.display()).into());
+    }
+    Ok(svg_paths)
+}
+
+fn draw_svg_grid(canvas: &mut Canvas, svgs: Vec<Dom>, width: i32, height: i32) -> usize {
+    let columns = (svgs.len() as f32).sqrt().ceil() as usize;
+    let rows = (svgs.len() + columns - 1) / columns;
+    let cell_width = width as f32 / columns as f32;
+    let cell_height = height as f32 / rows as f32;
+    let count = svgs.len();
+    for (index, mut svg) in svgs.into_iter().enumerate() {
+        let svg_size = svg.container_size();
+        let fit = if svg_size.is_empty() {
+            // Without an intrinsic size, let a viewBox-only SVG lay itself out in the cell.
+            svg.set_container_size((cell_width, cell_height));
+            1.0
+        } else {
+            (cell_width / svg_size.width).min(cell_height / svg_size.height)
+        };
+        canvas.save();
+        canvas.translate((
+            (index % columns) as f32 * cell_width,
+            (index / columns) as f32 * cell_height,
+        ));
+        canvas.scale((fit, fit));
+        svg.render(canvas);
+        canvas.restore();
+    }
+    count
+}
+
+pub(crate) fn data_from_file_path(file_path: &Path) -> std::io::Result<Data> {
+    bytes_from_file_path(file_path).map(|bytes| Data::new_copy(&bytes.as_slice()))
+}
+
+pub(crate) fn bytes_from_file_path(file_path: &Path) -> std::io::Result<Vec<u8>> {
+    let mut file = File::open(file_path)?;
+    let mut bytes = vec![];
+    file.read_to_end(&mut bytes).map(|_| bytes)
+}
+
+/// The encoded output file, and whether writing it was skipped because it was unchanged.
+struct SavedImage {
+    path: PathBuf,
+    skipped: bool,
+}
+
+/// Encodes the surface next to `output_stem` and returns the file saved, if any.
+fn save_image(
+    surface: &mut Surface,
+    output_stem: &Path,
+    mut format: OutputFormat,
+    quality: Option<u8>,
+    png_level: Option<u8>,
+    skip_unchanged: bool,
+) -> Result<SavedImage, BenchError> {
+    let mut context = surface.direct_context();
+    if let Some(context) = context.as_mut() {
+        context.flush_and_submit();
+    }
+    let image = surface.image_snapshot();
+    debug!("encoding {:?}...", format);
+    let mut encoded = match (format, png_level) {
+        (OutputFormat::Png, Some(level)) => {
+            let options = png_encoder::Options {
+                z_lib_level: i32::from(level),
+                ..png_encoder::Options::default()
+            };
+            png_encoder::encode_image(context.as_mut(), &image, &options)
+        }
+        _ => image.encode(
+            context.as_mut(),
+            format.encoded_format(),
+            quality.map(u32::from),
+        ),
+    };
+    if encoded.is_none() && format != OutputFormat::Png {
+        eprintln!("warning: {:?} encoding failed, falling back to PNG", format);
+        format = OutputFormat::Png;
+        encoded = image.encode(context.as_mut(), EncodedImageFormat::PNG, None);
+    }
+    let data = encoded.ok_or_else(|| BenchError::EncodeFailed(format!("{:?} output", format)))?;
+    let output_path = append_extension(output_stem, format.extension());
+    let bytes = data.as_bytes();
+    if skip_unchanged {
+        if let Ok(existing) = bytes_from_file_path(&output_path) {
+            if Sha256::digest(&existing) == Sha256::digest(bytes) {
+                debug!("{} is unchanged, not rewriting it", output_path.display());
+                return Ok(SavedImage {
+                    path: output_path,
+                    skipped: true,
+                });
+            }
+        }
+    }
+    std::fs::write(&output_path, bytes).map_err(|error| BenchError::io(&output_path, error))?;
+    Ok(SavedImage {
+        path: output_path,
+        skipped: false,
+    })
+}
+
+/// Saves the surface in each of `ENCODED_FORMATS`, returning every encode time and file size.
+fn encode_all(
+    surface: &mut Surface,
+    output_stem: &Path,
+    quality: Option<u8>,
+    png_level: Option<u8>,
+) -> Result<[(Duration, u64); 3], BenchError> {
+    let mut encodes = [(Duration::ZERO, 0); 3];
+    for (encode, format) in encodes.iter_mut().zip(ENCODED_FORMATS) {
+        let mut saved = None;
+        let duration = measure(|| {
+            saved = Some(save_image(
+                surface,
+                output_stem,
+                format,
+                quality,
+                png_level,
+                false,
+            ))
+        });
+        let size = match saved.transpose()? {
+            Some(saved) => std::fs::metadata(saved.path).map_or(0, |metadata| metadata.len()),
+            None => 0,
+        };
+        *encode = (duration, size);
+    }
+    Ok(encodes)
+}
+
+/// Checks that a saved PNG carries a color profile chunk that decodes to `expected`.
+///
+/// Skia tags sRGB output with an `sRGB` chunk and embeds other color spaces as an `iCCP` profile.
+fn verify_icc(saved_path: &Path, expected: &ColorSpace) -> Result<(), BenchError> {
+    let bytes =
+        bytes_from_file_path(saved_path).map_err(|error| BenchError::io(saved_path, error))?;
+    let chunk: &[u8] = if expected.is_srgb() { b"sRGB" } else { b"iCCP" };
+    if !bytes.windows(chunk.len()).any(|window| window == chunk) {
+        return Err(format!(
+            "{} has no {} chunk",
+            saved_path.display(),
+            String::from_utf8_lossy(chunk)
+        )
+        .into());
+    }
+    let decoded = Image::from_encoded(Data::new_copy(&bytes)).and_then(|image| image.color_space());
+    match decoded {
+        Some(color_space) if color_space == *expected => Ok(()),
+        _ => Err(format!(
+            "the color profile in {} does not match the requested color space",
+            saved_path.display()
+        )
+        .into()),
+    }
+}
+
+/// Warns when a `--transparent` render was written without a usable alpha channel.
+fn check_saved_alpha(saved_path: &Path) {
+    let image = data_from_file_path(saved_path)
+        .ok()
+        .and_then(Image::from_encoded);
+    match image {
+        Some(image) if image.alpha_type() != AlphaType::Opaque => {}
+        Some(_) => eprintln!(
+            "warning: {} has no alpha channel, use --format png or webp with --transparent",
+            saved_path.display()
+        ),
+        None => eprintln!(
+            "warning: could not decode {} to verify alpha",
+            saved_path.display()
+        ),
+    }
+}
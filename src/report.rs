@@ -0,0 +1,353 @@
+use crate::config::BenchmarkConfig;
+use crate::error::BenchError;
+use crate::timings::{millis, StageTimings};
+use crate::version::{version_info, VersionInfo};
+use memory_stats::MemoryStats;
+use serde::Serialize;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use std::time::Duration;
+
+pub(crate) fn format_memory(before: Option<MemoryStats>, after: Option<MemoryStats>) -> String {
+    match (before, after) {
+        (Some(before), Some(after)) => {
+            let delta = after.physical_mem as i64 - before.physical_mem as i64;
+            format!(
+                "memory: {} -> {} (delta: {:+.1}MiB)",
+                format_mebibytes(before.physical_mem),
+                format_mebibytes(after.physical_mem),
+                delta as f64 / (1024.0 * 1024.0)
+            )
+        }
+        _ => "memory stats unavailable".to_string(),
+    }
+}
+
+fn format_mebibytes(bytes: usize) -> String {
+    format!("{:.1}MiB", bytes as f64 / (1024.0 * 1024.0))
+}
+
+/// Opens `csv_path` for appending, writing the header to a new or empty file.
+///
+/// Fails if an existing file has a different header, since its columns would not line up.
+pub(crate) fn open_csv(csv_path: &Path) -> Result<File, BenchError> {
+    let failed = |error| BenchError::io(csv_path, error);
+    let columns: Vec<String> = StageTimings::default()
+        .stages()
+        .iter()
+        .map(|(name, _)| format!("{}_ms", name))
+        .collect();
+    let header = format!("iteration,scale,{},render_ms", columns.join(","));
+    let mut existing_header = String::new();
+    if csv_path.exists() {
+        let existing = File::open(csv_path).map_err(failed)?;
+        BufReader::new(existing)
+            .read_line(&mut existing_header)
+            .map_err(failed)?;
+    }
+    let existing_header = existing_header.trim_end();
+    if !existing_header.is_empty() && existing_header != header {
+        return Err(format!(
+            "{} has a different CSV header, use another --csv file",
+            csv_path.display()
+        )
+        .into());
+    }
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(csv_path)
+        .map_err(failed)?;
+    if existing_header.is_empty() {
+        writeln!(file, "{}", header).map_err(failed)?;
+    }
+    Ok(file)
+}
+
+pub(crate) fn write_csv_row(
+    file: &mut File,
+    iteration: usize,
+    scale: f32,
+    timings: &StageTimings,
+) -> Result<(), BenchError> {
+    let values: Vec<String> = timings
+        .stages()
+        .iter()
+        .map(|(_, duration)| format!("{:.3}", millis(*duration)))
+        .collect();
+    writeln!(
+        file,
+        "{},{},{},{:.3}",
+        iteration,
+        scale,
+        values.join(","),
+        millis(timings.render_time())
+    )
+    .map_err(|error| BenchError::Other(format!("could not write a CSV row: {}", error)))
+}
+
+#[derive(Serialize)]
+pub(crate) struct TimingRecord {
+    iteration: usize,
+    /// False for the iterations before `--measure-from`, which the summary leaves out.
+    measured: bool,
+    canvas_width: i32,
+    canvas_height: i32,
+    scale: f32,
+    surface_ns: u64,
+    clear_ns: u64,
+    path_ns: u64,
+    outline_ns: u64,
+    random_ns: u64,
+    shapes_ns: u64,
+    mesh_ns: u64,
+    micro_ns: u64,
+    fill_ns: u64,
+    raster_ns: u64,
+    decode_ns: u64,
+    mipbuild_ns: u64,
+    mipdraw_ns: u64,
+    blur_ns: u64,
+    filters_ns: u64,
+    text_ns: u64,
+    fallback_ns: u64,
+    nofallback_ns: u64,
+    textpath_ns: u64,
+    simpletext_ns: u64,
+    fonts_ns: u64,
+    reflow_ns: u64,
+    svg_ns: u64,
+    preset_ns: u64,
+    tiles_ns: u64,
+    stitch_ns: u64,
+    save_ns: u64,
+    record_ns: u64,
+    playback_ns: u64,
+    pdf_ns: u64,
+    upload_ns: u64,
+    readback_ns: u64,
+    svgout_ns: u64,
+    render_ns: u64,
+    saved_bytes: u64,
+}
+
+impl TimingRecord {
+    pub(crate) fn new(iteration: usize, config: &BenchmarkConfig, timings: &StageTimings) -> Self {
+        TimingRecord {
+            iteration,
+            measured: iteration >= usize::from(config.measure_from),
+            canvas_width: config.width,
+            canvas_height: config.height,
+            scale: config.scale,
+            surface_ns: timings.surface.as_nanos() as u64,
+            clear_ns: timings.clear.as_nanos() as u64,
+            path_ns: timings.path.as_nanos() as u64,
+            outline_ns: timings.outline.as_nanos() as u64,
+            random_ns: timings.random.as_nanos() as u64,
+            shapes_ns: timings.shapes.as_nanos() as u64,
+            mesh_ns: timings.mesh.as_nanos() as u64,
+            micro_ns: timings.micro.as_nanos() as u64,
+            fill_ns: timings.fill.as_nanos() as u64,
+            raster_ns: timings.raster.as_nanos() as u64,
+            decode_ns: timings.decode.as_nanos() as u64,
+            mipbuild_ns: timings.mip_build.as_nanos() as u64,
+            mipdraw_ns: timings.mip_draw.as_nanos() as u64,
+            blur_ns: timings.blur.as_nanos() as u64,
+            filters_ns: timings.filters.as_nanos() as u64,
+            text_ns: timings.text.as_nanos() as u64,
+            fallback_ns: timings.fallback.as_nanos() as u64,
+            nofallback_ns: timings.no_fallback.as_nanos() as u64,
+            textpath_ns: timings.text_path.as_nanos() as u64,
+            simpletext_ns: timings.simple_text.as_nanos() as u64,
+            fonts_ns: timings.fonts.as_nanos() as u64,
+            reflow_ns: timings.reflow.as_nanos() as u64,
+            svg_ns: timings.svg.as_nanos() as u64,
+            preset_ns: timings.preset.as_nanos() as u64,
+            tiles_ns: timings.tiles.as_nanos() as u64,
+            stitch_ns: timings.stitch.as_nanos() as u64,
+            save_ns: timings.save.as_nanos() as u64,
+            record_ns: timings.record.as_nanos() as u64,
+            playback_ns: timings.playback.as_nanos() as u64,
+            pdf_ns: timings.pdf.as_nanos() as u64,
+            upload_ns: timings.upload.as_nanos() as u64,
+            readback_ns: timings.readback.as_nanos() as u64,
+            svgout_ns: timings.svg_out.as_nanos() as u64,
+            render_ns: timings.render_time().as_nanos() as u64,
+            saved_bytes: timings.saved_bytes,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct JsonReport<'a> {
+    version_info: VersionInfo,
+    records: &'a [TimingRecord],
+}
+
+pub(crate) fn write_json(json_path: &Path, records: &[TimingRecord]) -> Result<(), BenchError> {
+    let file = File::create(json_path).map_err(|error| BenchError::io(json_path, error))?;
+    let report = JsonReport {
+        version_info: version_info(),
+        records,
+    };
+    serde_json::to_writer_pretty(file, &report)
+        .map_err(|error| BenchError::io(json_path, error.into()))
+}
+
+/// Prints `record` to stdout as a single line, so it can be consumed while the run continues.
+pub(crate) fn print_ndjson(record: &TimingRecord) {
+    println!("{}", serde_json::to_string(record).unwrap());
+}
+
+/// Inserts one row per iteration, tagged with the run's timestamp, creating the table on first use.
+///
+/// `timings` starts at iteration `first_iteration`, which is not 0 with `--measure-from`.
+#[cfg(feature = "rusqlite")]
+pub(crate) fn write_sqlite(
+    db_path: &Path,
+    config: &BenchmarkConfig,
+    timings: &[StageTimings],
+    first_iteration: usize,
+) -> Result<(), BenchError> {
+    use rusqlite::types::Value;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    let failed = |error: rusqlite::Error| {
+        BenchError::Other(format!("could not write {}: {}", db_path.display(), error))
+    };
+    let mut connection = rusqlite::Connection::open(db_path).map_err(failed)?;
+    let stage_columns: Vec<String> = StageTimings::default()
+        .stages()
+        .iter()
+        .map(|(name, _)| format!("{}_ns", name))
+        .chain(["render_ns".to_string()])
+        .collect();
+    let stage_schema: Vec<String> = stage_columns
+        .iter()
+        .map(|column| format!("{} INTEGER NOT NULL", column))
+        .collect();
+    connection
+        .execute(
+            &format!(
+                "CREATE TABLE IF NOT EXISTS timings (timestamp INTEGER NOT NULL, \
+                 iteration INTEGER NOT NULL, scale REAL NOT NULL, canvas_width INTEGER NOT NULL, \
+                 canvas_height INTEGER NOT NULL, crate_version TEXT NOT NULL, \
+                 skia_safe_version TEXT NOT NULL, os TEXT NOT NULL, arch TEXT NOT NULL, {})",
+                stage_schema.join(", ")
+            ),
+            [],
+        )
+        .map_err(failed)?;
+    let insert = format!(
+        "INSERT INTO timings (timestamp, iteration, scale, canvas_width, canvas_height, \
+         crate_version, skia_safe_version, os, arch, {}) VALUES ({})",
+        stage_columns.join(", "),
+        vec!["?"; 9 + stage_columns.len()].join(", ")
+    );
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs() as i64);
+    let info = version_info();
+    let transaction = connection.transaction().map_err(failed)?;
+    {
+        let mut statement = transaction.prepare(&insert).map_err(failed)?;
+        for (iteration, timing) in timings.iter().enumerate() {
+            let mut values: Vec<Value> = vec![
+                timestamp.into(),
+                ((first_iteration + iteration) as i64).into(),
+                f64::from(config.scale).into(),
+                i64::from(config.width).into(),
+                i64::from(config.height).into(),
+                info.crate_version.to_string().into(),
+                info.skia_safe_version.to_string().into(),
+                info.os.to_string().into(),
+                info.arch.to_string().into(),
+            ];
+            values.extend(
+                timing
+                    .stages()
+                    .iter()
+                    .map(|(_, duration)| Value::from(duration.as_nanos() as i64)),
+            );
+            values.push((timing.render_time().as_nanos() as i64).into());
+            statement
+                .execute(rusqlite::params_from_iter(values))
+                .map_err(failed)?;
+        }
+    }
+    transaction.commit().map_err(failed)
+}
+
+/// A Chrome Trace Event "complete" event, with times in microseconds.
+#[derive(Serialize)]
+pub(crate) struct TraceEvent {
+    name: String,
+    cat: &'static str,
+    ph: &'static str,
+    ts: f64,
+    dur: f64,
+    pid: u32,
+    tid: u32,
+}
+
+impl TraceEvent {
+    fn new(name: String, cat: &'static str, start: Duration, duration: Duration) -> Self {
+        TraceEvent {
+            name,
+            cat,
+            ph: "X",
+            ts: micros(start),
+            dur: micros(duration),
+            pid: std::process::id(),
+            tid: 0,
+        }
+    }
+}
+
+fn micros(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1_000_000.0
+}
+
+/// Builds an iteration span with its non-empty stages placed at the offsets they started at.
+pub(crate) fn trace_events(
+    iteration: usize,
+    start: Duration,
+    elapsed: Duration,
+    timings: &StageTimings,
+) -> Vec<TraceEvent> {
+    let mut events = vec![TraceEvent::new(
+        format!("iteration {}", iteration),
+        "iteration",
+        start,
+        elapsed,
+    )];
+    for ((name, duration), stage_start) in timings.stages().into_iter().zip(timings.starts.0) {
+        if let (false, Some(stage_start)) = (duration.is_zero(), stage_start) {
+            events.push(TraceEvent::new(
+                name.to_string(),
+                "stage",
+                start + stage_start,
+                duration,
+            ));
+        }
+    }
+    events
+}
+
+#[derive(Serialize)]
+struct TraceFile<'a> {
+    #[serde(rename = "traceEvents")]
+    trace_events: &'a [TraceEvent],
+}
+
+pub(crate) fn write_trace(trace_path: &Path, events: &[TraceEvent]) -> Result<(), BenchError> {
+    let file = File::create(trace_path).map_err(|error| BenchError::io(trace_path, error))?;
+    serde_json::to_writer(
+        file,
+        &TraceFile {
+            trace_events: events,
+        },
+    )
+    .map_err(|error| BenchError::io(trace_path, error.into()))
+}
@@ -0,0 +1,102 @@
+use crate::config::BenchmarkConfig;
+use crate::error::BenchError;
+use crate::run_benchmark;
+use crate::timings::{format_millis, StageStats};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Render time statistics of one run in a `--scale-sweep`.
+pub struct ScaleRun {
+    pub scale: f32,
+    pub size: (i32, i32),
+    pub render: StageStats,
+}
+
+pub struct ScaleSweep {
+    pub runs: Vec<ScaleRun>,
+}
+
+/// Runs the full benchmark once per `--scale-sweep` step, from `start` up to and including `end`.
+///
+/// Every step writes its own output files, tagged with its scale; `--csv` rows already carry it.
+pub fn run_scale_sweep(config: &BenchmarkConfig) -> Result<ScaleSweep, BenchError> {
+    let (start, end, step) = config
+        .scale_sweep
+        .ok_or_else(|| "--scale-sweep is not set".to_string())?;
+    let steps = ((end - start) / step + 1e-4).floor() as u32;
+    let mut runs = vec![];
+    for index in 0..=steps {
+        let mut scaled = config.clone();
+        scaled.scale = start + step * index as f32;
+        scaled.scale_sweep = None;
+        tag_outputs(&mut scaled);
+        let result = run_benchmark(&scaled)?;
+        let render = result
+            .summary
+            .stages
+            .iter()
+            .find(|(name, _)| *name == "render")
+            .map(|(_, stats)| *stats)
+            .unwrap_or_default();
+        runs.push(ScaleRun {
+            scale: scaled.scale,
+            size: scaled.surface_size(),
+            render,
+        });
+    }
+    Ok(ScaleSweep { runs })
+}
+
+/// Adds the scale to every output the benchmark overwrites, so the steps don't clobber each other.
+fn tag_outputs(config: &mut BenchmarkConfig) {
+    let scale = config.scale;
+    for path in [
+        &mut config.json_path,
+        &mut config.trace_path,
+        &mut config.pdf_path,
+        &mut config.svg_out,
+        &mut config.raw_path,
+        &mut config.diff_image,
+    ]
+    .into_iter()
+    .flatten()
+    {
+        *path = with_scale(path, scale);
+    }
+    if !config.output_template().contains("{scale}") {
+        config.save_template = Some(format!("{}-scale{{scale}}", config.output_template()));
+    }
+}
+
+/// Inserts `-scale<scale>` before the extension of `path`.
+fn with_scale(path: &Path, scale: f32) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let name = match path.extension() {
+        Some(extension) => format!("{}-scale{}.{}", stem, scale, extension.to_string_lossy()),
+        None => format!("{}-scale{}", stem, scale),
+    };
+    path.with_file_name(name)
+}
+
+impl fmt::Display for ScaleSweep {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:<8}{:>12}{:>10}{:>10}{:>10}{:>10}",
+            "scale", "size", "min", "max", "mean", "median"
+        )?;
+        for run in &self.runs {
+            write!(
+                f,
+                "\n{:<8}{:>12}{:>10}{:>10}{:>10}{:>10}",
+                run.scale,
+                format!("{}x{}", run.size.0, run.size.1),
+                format_millis(run.render.min),
+                format_millis(run.render.max),
+                format_millis(run.render.mean),
+                format_millis(run.render.median),
+            )?;
+        }
+        Ok(())
+    }
+}
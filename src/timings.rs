@@ -0,0 +1,498 @@
+use crate::config::{ColorFilterMode, ENCODED_FORMATS};
+use crate::error::BenchError;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+pub(crate) fn format_throughput(iterations: usize, elapsed: Duration) -> String {
+    format!(
+        "{} iterations in {} ({:.1} iterations/sec)",
+        iterations,
+        format_millis(elapsed),
+        iterations as f64 / elapsed.as_secs_f64()
+    )
+}
+
+/// Number of stages listed by `StageTimings::stages`.
+pub(crate) const STAGE_COUNT: usize = 33;
+
+/// Offsets from the start of an iteration at which each of `StageTimings::stages` first ran.
+#[derive(Clone, Copy, Debug)]
+pub struct StageStarts(pub [Option<Duration>; STAGE_COUNT]);
+
+impl Default for StageStarts {
+    fn default() -> Self {
+        StageStarts([None; STAGE_COUNT])
+    }
+}
+
+/// Per-stage durations of a single benchmark iteration; disabled stages stay at `Duration::ZERO`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct StageTimings {
+    pub surface: Duration,
+    pub clear: Duration,
+    pub path: Duration,
+    pub outline: Duration,
+    pub random: Duration,
+    pub shapes: Duration,
+    pub mesh: Duration,
+    pub micro: Duration,
+    pub fill: Duration,
+    pub raster: Duration,
+    pub decode: Duration,
+    pub mip_build: Duration,
+    pub mip_draw: Duration,
+    pub blur: Duration,
+    pub filters: Duration,
+    pub text: Duration,
+    pub fallback: Duration,
+    /// The `fallback` paragraph laid out with the primary font alone, for comparison.
+    pub no_fallback: Duration,
+    pub text_path: Duration,
+    pub simple_text: Duration,
+    pub fonts: Duration,
+    pub reflow: Duration,
+    pub svg: Duration,
+    pub preset: Duration,
+    pub tiles: Duration,
+    pub stitch: Duration,
+    pub save: Duration,
+    pub record: Duration,
+    pub playback: Duration,
+    pub pdf: Duration,
+    pub upload: Duration,
+    pub readback: Duration,
+    pub svg_out: Duration,
+    pub svg_count: usize,
+    pub text_count: u32,
+    pub image_draws: usize,
+    pub paths_parsed: usize,
+    pub paths_failed: usize,
+    pub transform_count: u32,
+    pub dashed: bool,
+    /// Whether only the `--dirty-rect` was redrawn.
+    pub partial: bool,
+    pub transformed: Duration,
+    pub tile_count: u32,
+    pub micro_draws: u32,
+    /// Device pixels covered by all `--fill-rate` circles together.
+    pub fill_pixels: u64,
+    pub fonts_registered: usize,
+    pub msaa_samples: u32,
+    pub glyph_cold: Duration,
+    pub glyph_warm: Duration,
+    pub saved_bytes: u64,
+    pub save_skipped: bool,
+    /// Encode time and file size for each of `ENCODED_FORMATS` with `--encode-all`.
+    pub encodes: [(Duration, u64); 3],
+    pub color_filter: Option<ColorFilterMode>,
+    /// When the iteration started; stage starts are only recorded once this is set.
+    pub origin: Option<Instant>,
+    /// When each stage first started, for `--trace`.
+    pub starts: StageStarts,
+}
+
+impl StageTimings {
+    pub fn stages(&self) -> [(&'static str, Duration); STAGE_COUNT] {
+        [
+            ("surface", self.surface),
+            ("clear", self.clear),
+            ("path", self.path),
+            ("outline", self.outline),
+            ("random", self.random),
+            ("shapes", self.shapes),
+            ("mesh", self.mesh),
+            ("micro", self.micro),
+            ("fill", self.fill),
+            ("raster", self.raster),
+            ("decode", self.decode),
+            ("mipbuild", self.mip_build),
+            ("mipdraw", self.mip_draw),
+            ("blur", self.blur),
+            ("filters", self.filters),
+            ("text", self.text),
+            ("fallback", self.fallback),
+            ("nofallback", self.no_fallback),
+            ("textpath", self.text_path),
+            ("simpletext", self.simple_text),
+            ("fonts", self.fonts),
+            ("reflow", self.reflow),
+            ("svg", self.svg),
+            ("preset", self.preset),
+            ("tiles", self.tiles),
+            ("stitch", self.stitch),
+            ("save", self.save),
+            ("record", self.record),
+            ("playback", self.playback),
+            ("pdf", self.pdf),
+            ("upload", self.upload),
+            ("readback", self.readback),
+            ("svgout", self.svg_out),
+        ]
+    }
+
+    /// Records that `stage` starts now, unless it already started earlier in the iteration.
+    pub(crate) fn mark_start(&mut self, stage: &str) {
+        let index = self.stages().iter().position(|(name, _)| *name == stage);
+        if let (Some(origin), Some(index)) = (self.origin, index) {
+            self.starts.0[index].get_or_insert_with(|| origin.elapsed());
+        }
+    }
+
+    /// Total drawing time, excluding surface setup, GPU transfers and the encode/write step.
+    ///
+    /// In picture mode the per-stage timings measure recording, so the playback is added on top.
+    /// In tile mode the per-stage timings stay empty and the tile and stitch times cover the scene.
+    pub fn render_time(&self) -> Duration {
+        self.path
+            + self.outline
+            + self.random
+            + self.shapes
+            + self.mesh
+            + self.micro
+            + self.fill
+            + self.raster
+            + self.decode
+            + self.mip_build
+            + self.mip_draw
+            + self.blur
+            + self.filters
+            + self.text
+            + self.fallback
+            + self.no_fallback
+            + self.text_path
+            + self.simple_text
+            + self.fonts
+            + self.reflow
+            + self.svg
+            + self.preset
+            + self.tiles
+            + self.stitch
+            + self.playback
+    }
+}
+
+impl fmt::Display for StageTimings {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let stages: Vec<String> = self
+            .stages()
+            .iter()
+            .map(|(name, duration)| format!("{}: {}", name, format_millis(*duration)))
+            .collect();
+        write!(
+            f,
+            "{} | render: {}",
+            stages.join(", "),
+            format_millis(self.render_time())
+        )?;
+        if self.text_count > 1 {
+            write!(
+                f,
+                " | text per paragraph: {}",
+                format_millis(self.text / self.text_count)
+            )?;
+        }
+        if self.svg_count > 0 {
+            write!(f, " | svgs rendered: {}", self.svg_count)?;
+        }
+        if self.tile_count > 0 {
+            write!(
+                f,
+                " | {} tiles, per tile: {}",
+                self.tile_count,
+                format_millis(self.tiles / self.tile_count)
+            )?;
+        }
+        if self.transform_count > 1 {
+            write!(
+                f,
+                " | {} transformed scenes: {}",
+                self.transform_count,
+                format_millis(self.transformed)
+            )?;
+        }
+        if !self.glyph_cold.is_zero() {
+            write!(
+                f,
+                " | glyph cache: cold {}, warm {}, delta {}",
+                format_millis(self.glyph_cold),
+                format_millis(self.glyph_warm),
+                format_millis(self.glyph_cold.saturating_sub(self.glyph_warm))
+            )?;
+        }
+        if self.msaa_samples > 0 {
+            write!(f, " | msaa: {}x", self.msaa_samples)?;
+        }
+        if !self.fonts.is_zero() {
+            write!(
+                f,
+                " | fonts registered: {} in {}",
+                self.fonts_registered,
+                format_millis(self.fonts)
+            )?;
+        }
+        if self.dashed {
+            write!(f, " | path dashed")?;
+        }
+        if self.partial {
+            write!(f, " | dirty rect only")?;
+        }
+        if self.paths_parsed + self.paths_failed > 0 {
+            write!(
+                f,
+                " | paths: {} parsed, {} failed",
+                self.paths_parsed, self.paths_failed
+            )?;
+        }
+        if self.micro_draws > 0 {
+            write!(
+                f,
+                " | micro: {} draws, {:.3}us per call, {:.0} draws/sec",
+                self.micro_draws,
+                self.micro.as_secs_f64() * 1_000_000.0 / self.micro_draws as f64,
+                self.micro_draws as f64 / self.micro.as_secs_f64()
+            )?;
+        }
+        if self.fill_pixels > 0 {
+            write!(
+                f,
+                " | fill rate: {:.1} megapixels/sec",
+                self.fill_pixels as f64 / 1_000_000.0 / self.fill.as_secs_f64()
+            )?;
+        }
+        if !self.fallback.is_zero() {
+            write!(
+                f,
+                " | font fallback cost: {}",
+                format_millis(self.fallback.saturating_sub(self.no_fallback))
+            )?;
+        }
+        if self.saved_bytes > 0 {
+            write!(f, " | saved: {:.1}KiB", self.saved_bytes as f64 / 1024.0)?;
+            if self.save_skipped {
+                write!(f, " (unchanged, write skipped)")?;
+            }
+        }
+        if self.encodes.iter().any(|(duration, _)| !duration.is_zero()) {
+            let encodes: Vec<String> = ENCODED_FORMATS
+                .iter()
+                .zip(self.encodes)
+                .map(|(format, (duration, size))| {
+                    format!(
+                        "{} {} ({:.1}KiB)",
+                        format.extension(),
+                        format_millis(duration),
+                        size as f64 / 1024.0
+                    )
+                })
+                .collect();
+            write!(f, " | encode: {}", encodes.join(", "))?;
+        }
+        if let Some(color_filter) = self.color_filter {
+            write!(f, " | color filter: {:?}", color_filter)?;
+        }
+        if self.image_draws > 1 {
+            write!(f, " | image draws: {}", self.image_draws)?;
+        }
+        Ok(())
+    }
+}
+
+pub(crate) fn format_millis(duration: Duration) -> String {
+    format!("{:.1}ms", millis(duration))
+}
+
+pub(crate) fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct StageStats {
+    pub min: Duration,
+    pub max: Duration,
+    pub mean: Duration,
+    pub median: Duration,
+    pub std_dev: Duration,
+}
+
+impl StageStats {
+    pub fn from_samples(samples: &[Duration]) -> Self {
+        if samples.is_empty() {
+            return StageStats::default();
+        }
+        let mut sorted = samples.to_vec();
+        sorted.sort();
+        let count = sorted.len() as f64;
+        let mean = sorted.iter().map(Duration::as_secs_f64).sum::<f64>() / count;
+        let variance = sorted
+            .iter()
+            .map(|sample| (sample.as_secs_f64() - mean).powi(2))
+            .sum::<f64>()
+            / count;
+        let middle = sorted.len() / 2;
+        let median = if sorted.len() % 2 == 0 {
+            (sorted[middle - 1] + sorted[middle]) / 2
+        } else {
+            sorted[middle]
+        };
+        StageStats {
+            min: sorted[0],
+            max: sorted[sorted.len() - 1],
+            mean: Duration::from_secs_f64(mean),
+            median,
+            std_dev: Duration::from_secs_f64(variance.sqrt()),
+        }
+    }
+}
+
+pub struct Summary {
+    pub iterations: usize,
+    pub stages: Vec<(&'static str, StageStats)>,
+    /// The 50th, 90th and 99th percentile of the per-iteration render time.
+    pub render_percentiles: [(u8, Duration); 3],
+}
+
+/// Nearest-rank percentile of already sorted samples.
+fn percentile(sorted: &[Duration], percent: u8) -> Duration {
+    if sorted.is_empty() {
+        return Duration::ZERO;
+    }
+    let rank = (sorted.len() * usize::from(percent) + 99) / 100;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+pub fn summarize(timings: &[StageTimings]) -> Summary {
+    let mut stages: Vec<(&'static str, StageStats)> = StageTimings::default()
+        .stages()
+        .iter()
+        .enumerate()
+        .map(|(index, (name, _))| {
+            let samples: Vec<Duration> = timings
+                .iter()
+                .map(|timing| timing.stages()[index].1)
+                .collect();
+            (*name, StageStats::from_samples(&samples))
+        })
+        .collect();
+    let mut render_samples: Vec<Duration> = timings.iter().map(StageTimings::render_time).collect();
+    stages.push(("render", StageStats::from_samples(&render_samples)));
+    render_samples.sort();
+    let render_percentiles =
+        [50, 90, 99].map(|percent| (percent, percentile(&render_samples, percent)));
+    Summary {
+        iterations: timings.len(),
+        stages,
+        render_percentiles,
+    }
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "summary over {} iteration(s):", self.iterations)?;
+        let width = self
+            .stages
+            .iter()
+            .map(|(name, _)| name.len())
+            .chain(["stage".len()])
+            .max()
+            .unwrap_or(0)
+            + 1;
+        write!(
+            f,
+            "{:<width$}{:>10}{:>10}{:>10}{:>10}{:>10}",
+            "stage",
+            "min",
+            "max",
+            "mean",
+            "median",
+            "stddev",
+            width = width
+        )?;
+        for (name, stats) in &self.stages {
+            write!(
+                f,
+                "\n{:<width$}{:>10}{:>10}{:>10}{:>10}{:>10}",
+                name,
+                format_millis(stats.min),
+                format_millis(stats.max),
+                format_millis(stats.mean),
+                format_millis(stats.median),
+                format_millis(stats.std_dev),
+                width = width
+            )?;
+        }
+        let percentiles: Vec<String> = self
+            .render_percentiles
+            .iter()
+            .map(|(percent, duration)| format!("p{}: {}", percent, format_millis(*duration)))
+            .collect();
+        write!(f, "\nrender percentiles: {}", percentiles.join(", "))
+    }
+}
+
+/// Iteration render times bucketed into equal-width bins between the fastest and slowest run.
+pub struct Histogram {
+    pub min: Duration,
+    pub bin_width: Duration,
+    pub counts: Vec<usize>,
+}
+
+impl Histogram {
+    pub fn new(timings: &[StageTimings], bins: usize) -> Self {
+        let samples: Vec<Duration> = timings.iter().map(StageTimings::render_time).collect();
+        let min = samples.iter().copied().min().unwrap_or_default();
+        let max = samples.iter().copied().max().unwrap_or_default();
+        let bins = bins.max(1);
+        let bin_width = (max - min) / bins as u32;
+        let mut counts = vec![0; bins];
+        for sample in samples {
+            let index = if bin_width.is_zero() {
+                0
+            } else {
+                ((sample - min).as_secs_f64() / bin_width.as_secs_f64()) as usize
+            };
+            counts[index.min(bins - 1)] += 1;
+        }
+        Histogram {
+            min,
+            bin_width,
+            counts,
+        }
+    }
+}
+
+impl fmt::Display for Histogram {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        const BAR_WIDTH: usize = 40;
+        let largest = self.counts.iter().copied().max().unwrap_or(0).max(1);
+        write!(f, "render time histogram:")?;
+        for (index, count) in self.counts.iter().enumerate() {
+            let start = self.min + self.bin_width * index as u32;
+            let end = start + self.bin_width;
+            write!(
+                f,
+                "\n{:>10} - {:<10} {:<width$} {}",
+                format_millis(start),
+                format_millis(end),
+                "#".repeat(count * BAR_WIDTH / largest),
+                count,
+                width = BAR_WIDTH
+            )?;
+        }
+        Ok(())
+    }
+}
+
+pub(crate) fn measure(stage: impl FnOnce()) -> Duration {
+    let start = Instant::now();
+    stage();
+    start.elapsed()
+}
+
+/// Like `measure`, for stages that can fail to load their inputs.
+pub(crate) fn try_measure(
+    stage: impl FnOnce() -> Result<(), BenchError>,
+) -> Result<Duration, BenchError> {
+    let start = Instant::now();
+    stage()?;
+    Ok(start.elapsed())
+}
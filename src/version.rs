@@ -0,0 +1,74 @@
+use serde::Serialize;
+use std::fmt;
+
+/// Build and host details to attach to benchmark results.
+#[derive(Clone, Debug, Serialize)]
+pub struct VersionInfo {
+    pub crate_version: &'static str,
+    pub skia_safe_version: &'static str,
+    pub os: &'static str,
+    pub arch: &'static str,
+    pub cpu_features: Vec<&'static str>,
+}
+
+pub fn format_cpu_features(features: &[&str]) -> String {
+    if features.is_empty() {
+        "none detected".to_string()
+    } else {
+        features.join(", ")
+    }
+}
+
+/// skia-safe doesn't expose the Skia milestone, so only its own version is reported.
+pub fn version_info() -> VersionInfo {
+    VersionInfo {
+        crate_version: env!("CARGO_PKG_VERSION"),
+        skia_safe_version: env!("SKIA_SAFE_VERSION"),
+        os: std::env::consts::OS,
+        arch: std::env::consts::ARCH,
+        cpu_features: cpu_features(),
+    }
+}
+
+/// SIMD extensions detected at runtime that Skia's CPU backend can take advantage of.
+pub fn cpu_features() -> Vec<&'static str> {
+    let mut features = vec![];
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    {
+        if is_x86_feature_detected!("sse2") {
+            features.push("sse2");
+        }
+        if is_x86_feature_detected!("sse4.1") {
+            features.push("sse4.1");
+        }
+        if is_x86_feature_detected!("avx") {
+            features.push("avx");
+        }
+        if is_x86_feature_detected!("avx2") {
+            features.push("avx2");
+        }
+        if is_x86_feature_detected!("avx512f") {
+            features.push("avx512f");
+        }
+    }
+    #[cfg(target_arch = "aarch64")]
+    {
+        if std::arch::is_aarch64_feature_detected!("neon") {
+            features.push("neon");
+        }
+    }
+    features
+}
+
+impl fmt::Display for VersionInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "rust-skia-performance-test {}", self.crate_version)?;
+        writeln!(f, "skia-safe {}", self.skia_safe_version)?;
+        writeln!(f, "os: {}, arch: {}", self.os, self.arch)?;
+        write!(
+            f,
+            "cpu features: {}",
+            format_cpu_features(&self.cpu_features)
+        )
+    }
+}
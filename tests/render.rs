@@ -0,0 +1,85 @@
+use rust_skia_performance_test::{render_frame, BenchError, BenchmarkConfig, CANVAS_SIZE};
+use skia_safe::Surface;
+
+fn assets_config() -> BenchmarkConfig {
+    BenchmarkConfig::new(concat!(env!("CARGO_MANIFEST_DIR"), "/assets"))
+}
+
+fn non_white_pixels(surface: &mut Surface) -> usize {
+    let image_info = surface.image_info();
+    let row_bytes = image_info.min_row_bytes();
+    let mut pixels = vec![0u8; row_bytes * image_info.height() as usize];
+    assert!(surface.read_pixels(&image_info, &mut pixels, row_bytes, (0, 0)));
+    pixels
+        .chunks_exact(4)
+        .filter(|pixel| pixel.iter().any(|&channel| channel != 0xff))
+        .count()
+}
+
+fn assert_renders(config: &BenchmarkConfig) {
+    let mut surface = render_frame(config).unwrap();
+    let expected_width = (config.width as f32 * config.scale).round() as i32;
+    let expected_height = (config.height as f32 * config.scale).round() as i32;
+    assert_eq!(surface.width(), expected_width);
+    assert_eq!(surface.height(), expected_height);
+    assert!(non_white_pixels(&mut surface) > 0, "surface is blank");
+}
+
+#[test]
+fn renders_path() {
+    let mut config = assets_config();
+    config.draw_path = true;
+    assert_renders(&config);
+}
+
+#[test]
+fn renders_raster() {
+    let mut config = assets_config();
+    config.draw_raster = true;
+    assert_renders(&config);
+}
+
+#[test]
+fn renders_text() {
+    let mut config = assets_config();
+    config.draw_text = true;
+    assert_renders(&config);
+}
+
+#[test]
+fn renders_svg() {
+    let mut config = assets_config();
+    config.draw_svg = true;
+    assert_renders(&config);
+}
+
+#[test]
+fn renders_all_stages() {
+    let config = assets_config();
+    assert_renders(&config);
+}
+
+#[test]
+fn renders_at_fractional_scale() {
+    let mut config = assets_config();
+    config.scale = 0.5;
+    assert_renders(&config);
+}
+
+#[test]
+fn renders_at_custom_size_and_scale() {
+    let mut config = assets_config();
+    config.width = CANVAS_SIZE / 2;
+    config.height = CANVAS_SIZE / 4;
+    config.scale = 2.0;
+    assert_renders(&config);
+}
+
+#[test]
+fn reports_missing_input_file() {
+    let mut config = BenchmarkConfig::new(concat!(env!("CARGO_MANIFEST_DIR"), "/missing"));
+    config.draw_path = true;
+    let error = render_frame(&config).err().expect("rendering should fail");
+    assert!(matches!(error, BenchError::FileNotFound(_)), "{}", error);
+    assert_eq!(error.exit_code(), 1);
+}